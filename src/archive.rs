@@ -0,0 +1,124 @@
+//! Saved games and replays, stored as JSON files in the user's data directory,
+//! one file per game. Each file has a `version` field so files of other versions
+//! of the format aren't misread.
+
+use std::{
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Deserialize;
+
+use crate::records;
+
+/// A directory of JSON files named after the time they were added. Only the
+/// newest files are kept.
+#[derive(Clone, Debug)]
+pub struct Archive {
+    dir: PathBuf,
+    /// How many files are kept. The oldest ones are deleted when adding more.
+    limit: usize,
+}
+
+impl Archive {
+    pub fn new(dir: PathBuf, limit: usize) -> Self {
+        Self { dir, limit }
+    }
+
+    /// The replays of finished games.
+    pub fn replays() -> Self {
+        Self::new(records::data_dir().join("replays"), 100)
+    }
+
+    /// Games saved to continue them later.
+    pub fn saves() -> Self {
+        Self::new(records::data_dir().join("saves"), 20)
+    }
+
+    /// Writes `json` to a new file named after the current time, then deletes
+    /// the oldest files over the limit. Returns the path of the new file.
+    pub fn add(&self, json: &str) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let mut millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut path = self.dir.join(format!("{millis}.json"));
+        while path.exists() {
+            millis += 1;
+            path = self.dir.join(format!("{millis}.json"));
+        }
+        fs::write(&path, json)?;
+
+        let files = self.files();
+        for old in &files[..files.len().saturating_sub(self.limit)] {
+            fs::remove_file(old)?;
+        }
+        Ok(path)
+    }
+
+    /// Returns the path of the most recently added file, if there is one.
+    pub fn latest(&self) -> Option<PathBuf> {
+        self.files().pop()
+    }
+
+    /// Returns the paths of all JSON files in the directory, oldest first.
+    fn files(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut files = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+            .collect::<Vec<_>>();
+        files.sort();
+        files.into_iter().map(|(_, path)| path).collect()
+    }
+}
+
+/// Checks that a file is in version `version` of its format, before parsing the
+/// rest of it. `kind` names what the file holds in errors, like "replay".
+pub fn check_version(kind: &str, version: u32, json: &str) -> io::Result<()> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+
+    let Version { version: found } = serde_json::from_str(json)?;
+    if found != version {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported {kind} version {found}"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn keeps_newest_files() {
+        let dir = env::temp_dir().join(format!("gridt-archive-{}", std::process::id()));
+        let archive = Archive::new(dir.clone(), 2);
+        let paths = (0..3)
+            .map(|i| archive.add(&i.to_string()).unwrap())
+            .collect::<Vec<_>>();
+        assert!(!paths[0].exists());
+        assert_eq!(archive.files(), paths[1..]);
+        assert_eq!(archive.latest(), Some(paths[2].clone()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let json = r#"{"version":2,"value":7}"#;
+        assert!(check_version("example", 2, json).is_ok());
+        assert!(check_version("example", 1, json).is_err());
+    }
+}
@@ -1,4 +1,5 @@
-use glam::{vec2, vec4, Vec2};
+use glam::{ivec2, vec2, vec4, IVec2, Vec2, Vec4};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     grid::Grid,
@@ -6,63 +7,179 @@ use crate::{
     tetromino::{FallingTetromino, Tetromino},
 };
 
+/// What a square of the board is occupied by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cell {
+    /// Part of a placed tetromino.
+    Tetromino(Tetromino),
+    /// Part of a garbage row.
+    Garbage,
+}
+
+impl Cell {
+    /// Returns the color the cell is drawn with.
+    pub fn color(self) -> Vec4 {
+        match self {
+            Cell::Tetromino(tetromino) => tetromino.color(),
+            Cell::Garbage => vec4(0.5, 0.5, 0.5, 1.0),
+        }
+    }
+}
+
 /// Represents the game board. Mainly a wrapper around `Grid` with convenience
 /// methods.
+///
+/// Row 0 is the top row of the visible board. Above it is a hidden buffer as
+/// tall as the visible board (negative rows) where tetrominoes spawn and the
+/// stack can grow into.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Board {
-    grid: Grid<Option<Tetromino>>,
+    grid: Grid<Option<Cell>>,
+    /// Number of hidden rows above the visible board.
+    buffer_height: usize,
 }
 
 impl Board {
-    pub const WIDTH: usize = 10;
-    pub const HEIGHT: usize = 20;
+    /// Width of a standard board.
+    pub const DEFAULT_WIDTH: usize = 10;
+    /// Visible height of a standard board.
+    pub const DEFAULT_HEIGHT: usize = 20;
+    /// Size of the border drawn around the board in pixels.
+    const BORDER_SIZE: f32 = 5.0;
 
-    /// Creates a new empty board.
-    pub fn empty() -> Self {
+    /// Creates a new empty board with `height` visible rows.
+    pub fn new(width: usize, height: usize) -> Self {
         Self {
-            grid: Grid::filled_with(None, Self::WIDTH, Self::HEIGHT),
+            grid: Grid::filled_with(None, width, 2 * height),
+            buffer_height: height,
+        }
+    }
+
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.grid.width()
+    }
+
+    /// Returns the number of visible rows.
+    pub fn height(&self) -> usize {
+        self.grid.height() - self.buffer_height
+    }
+
+    /// Checks whether the board has `width` columns and `height` visible rows,
+    /// with a hidden buffer as tall as the visible board.
+    pub fn has_size(&self, width: usize, height: usize) -> bool {
+        self.grid.width() == width
+            && self.buffer_height == height
+            && self.grid.height() == 2 * height
+    }
+
+    /// Returns the height of the stack in column `x`, counting from the bottom
+    /// of the board up to its highest occupied square.
+    pub fn column_height(&self, x: usize) -> usize {
+        (0..self.grid.height())
+            .find(|&y| self.grid.get(x, y).unwrap().is_some())
+            .map_or(0, |y| self.grid.height() - y)
+    }
+
+    /// Returns what occupies `square`, or `None` if it's empty or out of bounds.
+    pub fn cell(&self, square: IVec2) -> Option<Cell> {
+        self.get(square).flatten()
+    }
+
+    /// Returns the square at `square`, or `None` if it's out of bounds.
+    fn get(&self, square: IVec2) -> Option<Option<Cell>> {
+        let y = square.y + self.buffer_height as i32;
+        if square.x < 0 || y < 0 {
+            return None;
         }
+        self.grid.get(square.x as usize, y as usize).copied()
     }
 
     /// Checks wheter a falling tetromino can fit onto the board.
     pub fn can_fit(&self, tetromino: FallingTetromino) -> bool {
-        for square in tetromino.squares() {
-            // Allow tetrominos to stick out the top of the board to enable immediate
-            // rotation.
-            // FIXME The current handling of pieces sticking out the top is not ideal.
-            // If a piece is placed when sticking out the top, only part of it will get
-            // placed. If the player then manages to complete a row, the row is cleared
-            // and above rows shifted down, part of the piece will be missing.
-            if square.y < 0 && square.x >= 0 && square.x < Self::WIDTH as i32 {
-                continue;
-            }
-            let value = self.grid.get(square.x as usize, square.y as usize);
-            // The piece does not fit if it would be outside the bounds of the grid or if
-            // some of it's squares are already occupied.
-            if let None | Some(Some(_)) = value {
-                return false;
-            }
+        tetromino
+            .squares()
+            .into_iter()
+            .all(|square| !self.is_blocked(square))
+    }
+
+    /// Checks whether a square is outside the bounds of the board or already
+    /// occupied.
+    pub fn is_blocked(&self, square: IVec2) -> bool {
+        // The square is blocked if it's outside the bounds of the grid (including
+        // the buffer) or if it's already occupied.
+        matches!(self.get(square), None | Some(Some(_)))
+    }
+
+    /// Rotates a falling tetromino by `by` (see [`FallingTetromino::rotated`])
+    /// using its rotation system, trying each wall kick in order.
+    ///
+    /// Returns the rotated tetromino and the index of the kick that succeeded
+    /// (0 being the unkicked rotation), or `None` if no kick fits.
+    pub fn try_rotate(
+        &self,
+        tetromino: FallingTetromino,
+        by: i8,
+    ) -> Option<(FallingTetromino, usize)> {
+        tetromino.rotation_system.rotate(self, tetromino, by)
+    }
+
+    /// Returns the falling tetromino moved down as far as it fits.
+    pub fn drop_position(&self, tetromino: FallingTetromino) -> FallingTetromino {
+        let mut dropped = tetromino;
+        while self.can_fit(dropped.moved(ivec2(0, 1))) {
+            dropped = dropped.moved(ivec2(0, 1));
         }
-        true
+        dropped
     }
 
     /// Places a falling tetromino onto the board.
     ///
     /// # Panics
     ///
-    /// Panics if the tetromino is out of bounds of the board (except at the top).
+    /// Panics if the tetromino is out of bounds of the board and buffer.
     pub fn place(&mut self, tetromino: FallingTetromino) {
         for square in tetromino.squares() {
-            if square.y < 0 {
-                continue;
-            }
             self.grid.set(
                 square.x as usize,
-                square.y as usize,
-                Some(tetromino.tetromino),
+                (square.y + self.buffer_height as i32) as usize,
+                Some(Cell::Tetromino(tetromino.tetromino)),
             );
         }
     }
 
+    /// Pushes all rows up by one and adds a garbage row at the bottom, with an
+    /// empty square in column `hole`.
+    ///
+    /// Returns `false` if squares were pushed out of the top of the buffer.
+    pub fn add_garbage(&mut self, hole: usize) -> bool {
+        let overflow = self.grid.row_slice(0).iter().any(Option::is_some);
+
+        for y in 1..self.grid.height() {
+            for x in 0..self.grid.width() {
+                self.grid.set(x, y - 1, *self.grid.get(x, y).unwrap());
+            }
+        }
+        let bottom = self.grid.height() - 1;
+        for (x, cell) in self.grid.row_slice_mut(bottom).iter_mut().enumerate() {
+            *cell = (x != hole).then_some(Cell::Garbage);
+        }
+
+        !overflow
+    }
+
+    /// Returns the number of rows that contain garbage.
+    pub fn garbage_rows(&self) -> u32 {
+        (0..self.grid.height())
+            .filter(|&y| self.grid.row_slice(y).contains(&Some(Cell::Garbage)))
+            .count() as u32
+    }
+
+    /// Checks whether no square of the board is occupied.
+    pub fn is_empty(&self) -> bool {
+        self.grid.as_row_major().iter().all(Option::is_none)
+    }
+
     /// Clears complete rows and shifts above rows down. Returns the number of
     /// cleared rows.
     pub fn clear_complete(&mut self) -> u8 {
@@ -93,28 +210,162 @@ impl Board {
         rows_cleared
     }
 
-    /// Render the board.
-    pub fn render(&self, ctx: &mut RenderContext, offset: Vec2) {
+    /// Creates a board from rows of text (`#` for occupied squares), aligned to
+    /// the bottom of the board.
+    #[cfg(test)]
+    pub fn from_ascii(rows: &[&str]) -> Self {
+        let mut board = Self::default();
+        let top = board.grid.height() - rows.len();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    board.grid.set(x, top + y, Some(Cell::Garbage));
+                }
+            }
+        }
+        board
+    }
+
+    /// Returns the size of the board in pixels including its border, when drawn
+    /// with squares of `square_size` pixels.
+    pub fn render_size(&self, square_size: f32) -> Vec2 {
+        vec2(self.width() as f32, self.height() as f32) * square_size
+            + Vec2::splat(2.0 * Self::BORDER_SIZE)
+    }
+
+    /// Render the board with squares of `square_size` pixels.
+    pub fn render(&self, ctx: &mut RenderContext, offset: Vec2, square_size: f32) {
         ctx.quad_renderer.submit(Quad {
             position: offset,
-            // TODO This should be calculated from border size and tetromino square size.
-            size: vec2(310.0, 610.0),
+            size: self.render_size(square_size),
             fill_color: vec4(0.0, 0.0, 0.0, 0.0),
-            border_size: 5.0,
+            border_size: Self::BORDER_SIZE,
             border_color: vec4(0.8, 0.8, 0.8, 1.0),
         });
 
         let instances = self
             .grid
             .iter_with_indices()
-            .filter_map(|(x, y, sq)| sq.map(|t| (x, y, t)))
-            .map(|(x, y, t)| TetrominoSquare {
+            .filter_map(|(x, y, sq)| sq.map(|cell| (x, y, cell)))
+            // Skip the hidden buffer.
+            .filter_map(|(x, y, cell)| Some((x, y.checked_sub(self.buffer_height)?, cell)))
+            .map(|(x, y, cell)| TetrominoSquare {
                 position: offset
-                    // TODO This should be calculated from border size
-                    + Vec2::splat(5.0)
-                    + vec2(x as f32, y as f32) * Vec2::splat(TetrominoSquare::SIZE),
-                color: t.color(),
+                    + Vec2::splat(Self::BORDER_SIZE)
+                    + vec2(x as f32, y as f32) * Vec2::splat(square_size),
+                color: cell.color(),
+                size: square_size,
+                style: TetrominoSquare::SOLID,
             });
         ctx.square_renderer.submit_iter(instances);
     }
 }
+
+impl Default for Board {
+    /// Creates an empty standard 10x20 board.
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_WIDTH, Self::DEFAULT_HEIGHT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rotation::Srs;
+
+    use super::*;
+
+    fn spawn(tetromino: Tetromino) -> FallingTetromino {
+        FallingTetromino::spawn(tetromino, &Srs, Board::DEFAULT_WIDTH)
+    }
+
+    #[test]
+    fn rotate_without_kick() {
+        let board = Board::default();
+        let tetromino = spawn(Tetromino::T).moved(ivec2(0, 5));
+        let (rotated, kick) = board.try_rotate(tetromino, 1).unwrap();
+        assert_eq!(kick, 0);
+        assert_eq!(rotated.squares(), tetromino.rotated(1).squares());
+    }
+
+    #[test]
+    fn rotate_with_wall_kick() {
+        let board = Board::default();
+        // Vertical I against the right wall.
+        let tetromino = spawn(Tetromino::I).rotated(1).moved(ivec2(4, 5));
+        assert!(board.can_fit(tetromino));
+        assert!(!board.can_fit(tetromino.rotated(-1)));
+
+        let (rotated, kick) = board.try_rotate(tetromino, -1).unwrap();
+        assert_eq!(kick, 2);
+        assert_eq!(
+            rotated.squares(),
+            [ivec2(6, 6), ivec2(7, 6), ivec2(8, 6), ivec2(9, 6)]
+        );
+    }
+
+    #[test]
+    fn rotate_blocked() {
+        // Full board except for a vertical well at x = 0.
+        let board = Board::from_ascii(&[".#########"; Board::DEFAULT_HEIGHT]);
+        let tetromino = spawn(Tetromino::I).rotated(-1).moved(ivec2(-4, 10));
+        assert!(board.can_fit(tetromino));
+        assert!(board.try_rotate(tetromino, 1).is_none());
+    }
+
+    #[test]
+    fn drop_position() {
+        let board = Board::from_ascii(&["..........", "....#.....", "....##...."]);
+        let tetromino = spawn(Tetromino::T);
+        let dropped = board.drop_position(tetromino);
+        assert_eq!(
+            dropped.squares(),
+            [ivec2(4, 16), ivec2(3, 17), ivec2(4, 17), ivec2(5, 17)]
+        );
+        assert_eq!(board.drop_position(dropped).squares(), dropped.squares());
+    }
+
+    #[test]
+    fn place_in_buffer() {
+        let mut board = Board::from_ascii(&["##########"]);
+        // Vertical I mostly in the buffer.
+        let tetromino = spawn(Tetromino::I).rotated(1).moved(ivec2(-1, -3));
+        assert!(board.can_fit(tetromino));
+        board.place(tetromino);
+
+        // The part in the buffer is kept and moves down when rows are cleared.
+        assert_eq!(board.clear_complete(), 1);
+        assert!(!board.is_blocked(ivec2(4, -3)));
+        assert!(board.is_blocked(ivec2(4, -2)));
+        assert!(board.is_blocked(ivec2(4, 1)));
+    }
+
+    #[test]
+    fn buffer_bounds() {
+        let board = Board::new(6, 10);
+        assert_eq!(board.width(), 6);
+        assert_eq!(board.height(), 10);
+        assert!(!board.is_blocked(ivec2(5, -10)));
+        assert!(board.is_blocked(ivec2(5, -11)));
+        assert!(board.is_blocked(ivec2(6, 0)));
+        assert!(board.is_blocked(ivec2(0, 10)));
+        assert!(board.is_blocked(ivec2(-1, -1)));
+    }
+
+    #[test]
+    fn add_garbage() {
+        let mut board = Board::default();
+        board.place(spawn(Tetromino::O).moved(ivec2(0, 18)));
+        assert!(board.add_garbage(3));
+        assert!(board.add_garbage(7));
+        assert_eq!(board.garbage_rows(), 2);
+        assert!(!board.is_blocked(ivec2(3, 18)));
+        assert!(!board.is_blocked(ivec2(7, 19)));
+        assert!(board.is_blocked(ivec2(3, 19)));
+        // The stack moved up.
+        assert!(board.is_blocked(ivec2(5, 16)));
+        assert!(!board.is_blocked(ivec2(5, 15)));
+
+        let mut board = Board::from_ascii(&["#########."; 2 * Board::DEFAULT_HEIGHT]);
+        assert!(!board.add_garbage(0));
+    }
+}
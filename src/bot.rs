@@ -0,0 +1,198 @@
+//! A computer player that picks placements with a weighted heuristic.
+
+use std::collections::VecDeque;
+
+use glam::ivec2;
+
+use crate::{
+    board::Board, game::Game, input::Input, movegen::placements, tetromino::FallingTetromino,
+};
+
+/// How much each property of the board after a placement counts towards its
+/// score. Negative weights are penalties.
+#[derive(Clone, Copy, Debug)]
+pub struct Weights {
+    /// Sum of the heights of all columns.
+    pub aggregate_height: f32,
+    /// Empty squares below the top of their column.
+    pub holes: f32,
+    /// Sum of the height differences between neighboring columns.
+    pub bumpiness: f32,
+    /// Sum of how far columns are below both their neighbors.
+    pub wells: f32,
+    /// Rows cleared by the placement.
+    pub lines: f32,
+}
+
+impl Default for Weights {
+    /// Weights that keep the stack low and flat, for surviving as long as
+    /// possible.
+    fn default() -> Self {
+        Self {
+            aggregate_height: -0.51,
+            holes: -0.36,
+            bumpiness: -0.18,
+            wells: -0.1,
+            lines: 0.76,
+        }
+    }
+}
+
+/// Scores the board after placing `tetromino` and clearing rows. Higher is
+/// better.
+fn evaluate(board: &Board, tetromino: FallingTetromino, weights: &Weights) -> f32 {
+    let mut board = board.clone();
+    board.place(tetromino);
+    let lines = board.clear_complete();
+
+    let heights = (0..board.width())
+        .map(|x| board.column_height(x))
+        .collect::<Vec<_>>();
+    let bottom = board.height() as i32;
+    let holes = heights
+        .iter()
+        .enumerate()
+        .map(|(x, &height)| {
+            (bottom - height as i32..bottom)
+                .filter(|&y| !board.is_blocked(ivec2(x as i32, y)))
+                .count()
+        })
+        .sum::<usize>();
+    let bumpiness = heights
+        .windows(2)
+        .map(|pair| pair[0].abs_diff(pair[1]))
+        .sum::<usize>();
+    // The walls count as columns as high as the board.
+    let wells = (0..heights.len())
+        .map(|x| {
+            let left = x
+                .checked_sub(1)
+                .map_or(board.height(), |left| heights[left]);
+            let right = heights.get(x + 1).copied().unwrap_or(board.height());
+            left.min(right).saturating_sub(heights[x])
+        })
+        .sum::<usize>();
+
+    weights.aggregate_height * heights.iter().sum::<usize>() as f32
+        + weights.holes * holes as f32
+        + weights.bumpiness * bumpiness as f32
+        + weights.wells * wells as f32
+        + weights.lines * lines as f32
+}
+
+/// Plays a game by choosing the best reachable placement for each tetromino,
+/// possibly after holding, and pressing the inputs that lead there.
+pub struct Bot {
+    weights: Weights,
+    /// Ticks between inputs. Higher is slower and easier to beat, 0 places
+    /// every tetromino right away.
+    input_delay: u32,
+    /// Ticks since the last input.
+    ticks: u32,
+    /// Inputs left to press for the falling tetromino.
+    plan: VecDeque<Input>,
+    /// Number of tetrominoes placed when the plan was made. Once another one is
+    /// placed the plan is outdated.
+    planned_at: Option<u32>,
+}
+
+impl Bot {
+    pub fn new(weights: Weights, input_delay: u32) -> Self {
+        Self {
+            weights,
+            input_delay,
+            ticks: 0,
+            plan: VecDeque::new(),
+            planned_at: None,
+        }
+    }
+
+    /// Returns the inputs leading to the best placement of the falling or the
+    /// hold tetromino.
+    pub fn best_inputs(&self, game: &Game) -> Vec<Input> {
+        let board = game.board();
+        let mut candidates = vec![(game.falling_tetromino(), Vec::new())];
+        if let Some(held) = game.hold_candidate() {
+            candidates.push((game.spawned(held), vec![Input::Hold]));
+        }
+
+        let mut best = None;
+        let mut best_score = f32::NEG_INFINITY;
+        for (tetromino, prefix) in candidates {
+            for placement in placements(board, tetromino) {
+                let score = evaluate(board, placement.tetromino, &self.weights);
+                if score > best_score {
+                    best_score = score;
+                    best = Some([prefix.as_slice(), &placement.inputs].concat());
+                }
+            }
+        }
+        best.unwrap_or_default()
+    }
+
+    /// Plans the placement of new tetrominoes and presses the next input once
+    /// the input delay passed. Should be called before every tick of the game.
+    pub fn update(&mut self, game: &mut Game) {
+        if game.is_over() {
+            return;
+        }
+        let pieces = game.progress().statistics.pieces;
+        if self.planned_at != Some(pieces) {
+            self.plan = self.best_inputs(game).into();
+            self.planned_at = Some(pieces);
+        }
+
+        if self.input_delay == 0 {
+            while let Some(input) = self.plan.pop_front() {
+                game.press(input);
+                game.release(input);
+            }
+            return;
+        }
+        self.ticks += 1;
+        if self.ticks < self.input_delay {
+            return;
+        }
+        self.ticks = 0;
+        if let Some(input) = self.plan.pop_front() {
+            game.press(input);
+            game.release(input);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rotation::Srs, settings::Settings, tetromino::Tetromino};
+
+    #[test]
+    fn prefers_clearing_lines() {
+        let board = Board::from_ascii(&["######### "; 4]);
+        let i = FallingTetromino::spawn(Tetromino::I, &Srs, Board::DEFAULT_WIDTH);
+        let best = placements(&board, i)
+            .into_iter()
+            .max_by(|a, b| {
+                let a = evaluate(&board, a.tetromino, &Weights::default());
+                let b = evaluate(&board, b.tetromino, &Weights::default());
+                a.total_cmp(&b)
+            })
+            .unwrap();
+        assert!(best.tetromino.squares().iter().all(|square| square.x == 9));
+    }
+
+    #[test]
+    fn survives() {
+        let mut game = Game::new(Settings {
+            seed: Some(5),
+            ..Settings::default()
+        });
+        let mut bot = Bot::new(Weights::default(), 0);
+        for _ in 0..500 {
+            bot.update(&mut game);
+            game.update();
+        }
+        assert!(!game.is_over());
+        assert!(game.progress().lines > 100);
+    }
+}
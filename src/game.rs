@@ -1,49 +1,296 @@
-use glam::{ivec2, vec2, vec4, IVec2, Vec2};
+use std::{fs, io};
+
+use glam::{ivec2, vec2, vec4, IVec2, Vec2, Vec4};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use wgpu::SurfaceError;
-use wgpu_glyph::{HorizontalAlign, Layout, Section, Text};
+use wgpu_glyph::{HorizontalAlign, Layout, Section, Text, VerticalAlign};
 use winit::event::{ElementState, KeyboardInput};
 
 use crate::{
+    archive::{self, Archive},
     board::Board,
     game_over::GameOver,
+    input::{Input, KeyMap},
+    main_menu::MainMenu,
+    mode::{GameMode, Marathon, Panel, Progress},
+    randomizer::{PieceQueue, Randomizer},
     render::{context::RenderContext, quad::Quad, square::TetrominoSquare},
+    replay::{InputEvent, Recording},
     scene::{Action, Scene},
+    scoring::{ScoreBreakdown, Scoring, TSpin},
+    settings::{LockReset, Settings},
+    statistics::Statistics,
     tetromino::{FallingTetromino, Tetromino},
 };
 
 /// An in-progress game.
+///
+/// The mode and key map aren't serialized with the game. Use [`Game::save`] and
+/// [`Game::load_save`] to save and continue games.
+#[derive(Serialize, Deserialize)]
 pub struct Game {
+    settings: Settings,
+    /// Decides when the game ends and what the HUD shows.
+    #[serde(skip, default = "Game::unset_mode")]
+    mode: Box<dyn GameMode>,
+    #[serde(skip)]
+    key_map: KeyMap,
+    seed: u64,
+    /// Source of all randomness in the game except garbage holes, so that the
+    /// same seed and inputs always play out the same way.
+    rng: ChaCha8Rng,
+    /// Source of garbage holes, seeded like `rng` but a separate stream. This
+    /// way games with the same seed are dealt the same tetrominoes, however
+    /// much garbage each of them receives.
+    garbage_rng: ChaCha8Rng,
     board: Board,
     falling_tetromino: FallingTetromino,
-    next_tetromino: Tetromino,
-    ticks_elapsed: usize,
+    queue: PieceQueue,
+    held_tetromino: Option<Tetromino>,
+    /// Whether hold was already used for the falling tetromino.
+    hold_locked: bool,
+    /// How far the falling tetromino has fallen towards the next row, in cells.
+    fall_progress: f32,
+    /// Ticks the falling tetromino has been resting on the stack.
+    lock_ticks: u32,
+    /// How often the lock delay was reset by moving or rotating.
+    lock_resets: u32,
+    /// Whether the falling tetromino touched the stack or the floor since it
+    /// reached `lowest_row`. Only moves and rotations after that use up lock
+    /// resets, even once it's in the air again.
+    touched_down: bool,
+    /// The lowest row the falling tetromino's grid has reached.
+    lowest_row: i32,
+    /// The kick used by the last rotation, if the last successful action was a
+    /// rotation. Needed to detect T-spins.
+    last_rotation_kick: Option<usize>,
+    /// Inputs currently held down, in the order they were pressed.
+    held_inputs: Vec<Input>,
+    /// Ticks since the current horizontal movement key was pressed, for auto
+    /// shift.
+    shift_ticks: u32,
     score: u32,
+    scoring: Scoring,
+    statistics: Statistics,
+    /// The most recent line clear, shown until `clear_display_ticks` run out.
+    last_clear: Option<ScoreBreakdown>,
+    clear_display_ticks: u32,
     level: u32,
     rows_cleared: u32,
-    lost: bool,
+    /// Ticks played so far. Stops counting once the game ends.
+    ticks_played: u64,
+    garbage_cleared: u32,
+    /// Garbage attacks received in versus that haven't risen yet, in rows,
+    /// oldest first.
+    incoming_garbage: Vec<u32>,
+    /// Garbage rows sent in versus that the opponent hasn't received yet.
+    outgoing_garbage: u32,
+    /// Whether the goal of the mode was reached.
+    finished: bool,
+    /// Why the game ended, if it did.
+    top_out: Option<TopOut>,
+    /// Every input pressed and released so far, for replays.
+    input_events: Vec<InputEvent>,
+    /// Whether the game is paused, only when played as its own scene.
+    #[serde(skip)]
+    paused: bool,
+    /// Where the replay is saved once the game ends as its own scene, if
+    /// anywhere.
+    #[serde(skip)]
+    replays: Option<Archive>,
+}
+
+/// The ways a game can end because the stack got too high.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TopOut {
+    /// A newly spawned tetromino overlaps the stack.
+    BlockOut,
+    /// A tetromino was placed completely above the visible board.
+    LockOut,
+    /// Garbage pushed the stack out of the top of the buffer.
+    Overflow,
 }
 
 impl Game {
-    /// Starts a new game starting at level 0.
-    pub fn new() -> Self {
-        Self {
-            board: Board::empty(),
-            falling_tetromino: FallingTetromino::random_at_origin(),
-            next_tetromino: Tetromino::random(),
-            ticks_elapsed: 0,
+    /// Starts a new game at `settings.start_level`. The game is seeded with
+    /// `settings.seed`, or a random seed if there is none.
+    pub fn new(settings: Settings) -> Self {
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut queue = PieceQueue::new(
+            settings.randomizer.build(),
+            settings.preview_count,
+            &mut rng,
+        );
+        let first = queue.generate(&mut rng);
+        let mut garbage_rng = ChaCha8Rng::seed_from_u64(seed);
+        garbage_rng.set_stream(1);
+        let mut game = Self {
+            settings,
+            mode: settings.mode.build(),
+            key_map: KeyMap::default(),
+            seed,
+            board: Board::new(settings.board_width, settings.board_height),
+            falling_tetromino: FallingTetromino::spawn(
+                first,
+                settings.rotation_system,
+                settings.board_width,
+            ),
+            queue,
+            held_tetromino: None,
+            hold_locked: false,
+            rng,
+            garbage_rng,
+            fall_progress: 0.0,
+            lock_ticks: 0,
+            lock_resets: 0,
+            touched_down: false,
+            lowest_row: 0,
+            last_rotation_kick: None,
+            held_inputs: Vec::new(),
+            shift_ticks: 0,
             score: 0,
-            level: 0,
+            scoring: Scoring::default(),
+            statistics: Statistics::default(),
+            last_clear: None,
+            clear_display_ticks: 0,
+            level: settings.start_level,
             rows_cleared: 0,
-            lost: false,
+            ticks_played: 0,
+            garbage_cleared: 0,
+            incoming_garbage: Vec::new(),
+            outgoing_garbage: 0,
+            finished: false,
+            top_out: None,
+            input_events: Vec::new(),
+            paused: false,
+            replays: None,
+        };
+        let garbage = game.mode.initial_garbage();
+        game.add_garbage(garbage);
+        game.spawn(first);
+        game
+    }
+
+    /// Replaces the keys this game is controlled with.
+    pub fn with_key_map(mut self, key_map: KeyMap) -> Self {
+        self.key_map = key_map;
+        self
+    }
+
+    /// Saves the replay of this game in `replays` once it ends, if there is one.
+    /// Games and the games restarted from them don't save replays otherwise.
+    pub fn with_replays(mut self, replays: Option<Archive>) -> Self {
+        self.replays = replays;
+        self
+    }
+
+    /// Returns the seed this game was started with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the board, without the falling tetromino.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Returns the tetromino the player currently controls.
+    pub fn falling_tetromino(&self) -> FallingTetromino {
+        self.falling_tetromino
+    }
+
+    /// Returns the held tetromino, if any.
+    pub fn held_tetromino(&self) -> Option<Tetromino> {
+        self.held_tetromino
+    }
+
+    /// Returns the upcoming tetrominoes shown in the next queue.
+    pub fn queue(&self) -> impl Iterator<Item = Tetromino> + '_ {
+        self.queue.peek()
+    }
+
+    /// Returns the combo and back-to-back state.
+    pub fn scoring(&self) -> &Scoring {
+        &self.scoring
+    }
+
+    /// Returns the tetromino holding would bring into play, or `None` if hold
+    /// can't be used right now.
+    pub fn hold_candidate(&self) -> Option<Tetromino> {
+        if self.hold_locked {
+            return None;
         }
+        self.held_tetromino.or_else(|| self.queue.peek().next())
     }
 
-    /// Rotates the falling tetromino if possible.
-    fn try_rotate(&mut self, by: i8) {
-        let rotated = self.falling_tetromino.rotated(by);
-        if self.board.can_fit(rotated) {
-            self.falling_tetromino = rotated;
+    /// Checks whether the game ended, either by topping out or by reaching the
+    /// goal of the mode.
+    pub fn is_over(&self) -> bool {
+        self.top_out.is_some() || self.finished
+    }
+
+    /// Presses or releases the input bound to a key in this game's key map.
+    /// Returns whether the key is bound.
+    pub fn key_input(&mut self, input: KeyboardInput) -> bool {
+        let Some(game_input) = self.key_map.input(input.scancode) else {
+            return false;
+        };
+        match input.state {
+            ElementState::Pressed => self.press(game_input),
+            ElementState::Released => self.release(game_input),
+        }
+        true
+    }
+
+    /// Advances the game by a tick. Does nothing once the game is over.
+    pub fn update(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        self.ticks_played += 1;
+        self.update_mode();
+        if self.finished {
+            return;
         }
+        let rising = self.mode.rising_garbage(&self.progress());
+        self.add_garbage(rising);
+
+        self.clear_display_ticks = self.clear_display_ticks.saturating_sub(1);
+        self.update_auto_shift();
+        self.update_gravity();
+        self.update_lock_delay();
+    }
+
+    /// Queues garbage sent by the opponent in versus. It rises once a tetromino
+    /// is placed without clearing lines, unless line clears cancel it first.
+    pub fn receive_garbage(&mut self, rows: u32) {
+        if rows > 0 {
+            self.incoming_garbage.push(rows);
+        }
+    }
+
+    /// Returns the garbage rows sent to the opponent since the last call.
+    pub fn take_outgoing_garbage(&mut self) -> u32 {
+        std::mem::take(&mut self.outgoing_garbage)
+    }
+
+    /// Returns the number of received garbage rows waiting to rise.
+    pub fn incoming_garbage(&self) -> u32 {
+        self.incoming_garbage.iter().sum()
+    }
+
+    /// Rotates the falling tetromino if possible, applying wall kicks. Returns the
+    /// index of the kick that was used.
+    fn try_rotate(&mut self, by: i8) -> Option<usize> {
+        let (rotated, kick) = self.board.try_rotate(self.falling_tetromino, by)?;
+        self.falling_tetromino = rotated;
+        self.last_rotation_kick = Some(kick);
+        self.reset_lock_delay();
+        Some(kick)
     }
 
     /// Moves the falling tetromino if possible.
@@ -51,11 +298,8 @@ impl Game {
         let moved = self.falling_tetromino.moved(by);
         if self.board.can_fit(moved) {
             self.falling_tetromino = moved;
-
-            // Reset tick counter after successfully moving down.
-            if by.y > 0 {
-                self.ticks_elapsed = 0;
-            }
+            self.last_rotation_kick = None;
+            self.reset_lock_delay();
 
             true
         } else {
@@ -63,147 +307,833 @@ impl Game {
         }
     }
 
+    /// Handles an input being pressed. Presses of inputs that are already held
+    /// (like key repeats) are ignored, and aren't recorded.
+    pub fn press(&mut self, input: Input) {
+        if self.held_inputs.contains(&input) {
+            return;
+        }
+        self.record(input, true);
+        self.held_inputs.push(input);
+
+        match input {
+            Input::MoveLeft | Input::MoveRight => {
+                self.shift_ticks = 0;
+                self.try_move(ivec2(Self::shift_direction(input), 0));
+            }
+            Input::SoftDrop => {
+                if self.try_move(ivec2(0, 1)) {
+                    self.fall_progress = 0.0;
+                    self.score += Scoring::soft_drop(1);
+                }
+            }
+            Input::HardDrop => self.drop(),
+            Input::RotateClockwise => {
+                self.try_rotate(1);
+            }
+            Input::RotateCounterclockwise => {
+                self.try_rotate(-1);
+            }
+            Input::Hold => self.hold(),
+        }
+    }
+
+    /// Handles an input being released. Inputs that aren't held are ignored.
+    pub fn release(&mut self, input: Input) {
+        let Some(index) = self.held_inputs.iter().position(|&held| held == input) else {
+            return;
+        };
+        self.record(input, false);
+        self.held_inputs.remove(index);
+
+        // Auto shift starts over when falling back to the other direction.
+        if let Input::MoveLeft | Input::MoveRight = input {
+            self.shift_ticks = 0;
+        }
+    }
+
+    /// Records an input for replays, at the tick it happened before.
+    fn record(&mut self, input: Input, pressed: bool) {
+        self.input_events.push(InputEvent {
+            tick: self.ticks_played,
+            input,
+            pressed,
+        });
+    }
+
+    /// Returns everything needed to play this game back.
+    pub fn recording(&self) -> Recording {
+        Recording {
+            version: Recording::VERSION,
+            settings: Settings {
+                seed: Some(self.seed),
+                ..self.settings
+            },
+            ticks: self.ticks_played,
+            events: self.input_events.clone(),
+            opponent_events: None,
+        }
+    }
+
+    /// Releases all held inputs, as if every key was let go.
+    fn release_all(&mut self) {
+        for input in self.held_inputs.clone() {
+            self.release(input);
+        }
+    }
+
+    /// Returns the horizontal direction an input moves in.
+    fn shift_direction(input: Input) -> i32 {
+        match input {
+            Input::MoveLeft => -1,
+            Input::MoveRight => 1,
+            _ => 0,
+        }
+    }
+
+    /// Repeats horizontal movement while a movement key is held, after the delayed
+    /// auto shift (DAS) and then every auto repeat rate (ARR) ticks.
+    fn update_auto_shift(&mut self) {
+        // The most recently pressed direction wins.
+        let Some(&input) = self
+            .held_inputs
+            .iter()
+            .rev()
+            .find(|input| matches!(input, Input::MoveLeft | Input::MoveRight))
+        else {
+            return;
+        };
+        let by = ivec2(Self::shift_direction(input), 0);
+
+        self.shift_ticks += 1;
+        if self.shift_ticks < self.settings.das {
+            return;
+        }
+        if self.settings.arr == 0 {
+            while self.try_move(by) {}
+        } else if (self.shift_ticks - self.settings.das).is_multiple_of(self.settings.arr) {
+            self.try_move(by);
+        }
+    }
+
+    /// Resets the lock delay after the falling tetromino moved or rotated, if the
+    /// lock reset policy allows it.
+    fn reset_lock_delay(&mut self) {
+        let row = self.falling_tetromino.position().y;
+        if row > self.lowest_row {
+            self.lowest_row = row;
+            self.lock_ticks = 0;
+            self.lock_resets = 0;
+            self.touched_down = self.is_grounded();
+            return;
+        }
+
+        if !self.touched_down {
+            self.touched_down = self.is_grounded();
+            return;
+        }
+        match self.settings.lock_reset {
+            LockReset::Move { limit } if self.lock_resets < limit => {
+                self.lock_ticks = 0;
+                self.lock_resets += 1;
+            }
+            LockReset::Move { .. } | LockReset::Step => (),
+            LockReset::Infinite => self.lock_ticks = 0,
+        }
+    }
+
+    /// Checks whether the falling tetromino is resting on the stack or the floor.
+    fn is_grounded(&self) -> bool {
+        !self
+            .board
+            .can_fit(self.falling_tetromino.moved(ivec2(0, 1)))
+    }
+
     /// Drops the falling tetromino and places it immediately.
     fn drop(&mut self) {
-        while self.try_move(ivec2(0, 1)) {}
+        let dropped = self.board.drop_position(self.falling_tetromino);
+        let cells = dropped.position().y - self.falling_tetromino.position().y;
+        self.score += Scoring::hard_drop(cells as u32);
+        self.falling_tetromino = dropped;
+        if cells > 0 {
+            self.last_rotation_kick = None;
+        }
         self.finalize();
     }
 
     /// Places the falling tetromino and spawns a new one.
     fn finalize(&mut self) {
+        if self
+            .falling_tetromino
+            .squares()
+            .iter()
+            .all(|square| square.y < 0)
+        {
+            self.top_out = Some(TopOut::LockOut);
+            return;
+        }
+
+        let t_spin = self.t_spin();
+        let garbage_rows = self.board.garbage_rows();
         self.board.place(self.falling_tetromino);
         let rows_cleared = self.board.clear_complete();
+        self.garbage_cleared += garbage_rows - self.board.garbage_rows();
         self.rows_cleared += rows_cleared as u32;
-        self.score += calc_score(rows_cleared);
+        let perfect_clear = self.board.is_empty();
+        let breakdown = self
+            .scoring
+            .clear(rows_cleared, t_spin, perfect_clear, self.level);
+        self.statistics.record(breakdown.as_ref());
+        if let Some(breakdown) = breakdown {
+            self.score += breakdown.total();
+            self.last_clear = Some(breakdown);
+            self.clear_display_ticks = Self::CLEAR_DISPLAY_TICKS;
+            self.attack(breakdown.attack());
+        }
+        if rows_cleared == 0 {
+            self.raise_incoming_garbage();
+        }
+        self.level = self.settings.start_level + self.rows_cleared / self.settings.lines_per_level;
+        self.update_mode();
+        if self.finished {
+            return;
+        }
 
-        self.falling_tetromino = FallingTetromino::new_at_origin(self.next_tetromino);
-        self.next_tetromino = Tetromino::random();
+        let next = self.queue.generate(&mut self.rng);
+        self.spawn(next);
+        self.hold_locked = false;
+    }
+
+    /// Returns what happened in the game so far.
+    pub fn progress(&self) -> Progress {
+        Progress {
+            score: self.score,
+            level: self.level,
+            lines: self.rows_cleared,
+            ticks: self.ticks_played,
+            garbage_rows: self.board.garbage_rows(),
+            garbage_cleared: self.garbage_cleared,
+            statistics: self.statistics,
+        }
+    }
+
+    /// Lets the mode check whether its goal was reached.
+    fn update_mode(&mut self) {
+        let progress = self.progress();
+        self.finished = self.mode.update(&progress);
+    }
+
+    /// Adds garbage rows below the stack, each with a hole in a random column.
+    fn add_garbage(&mut self, rows: u32) {
+        let holes = (0..rows)
+            .map(|_| self.garbage_rng.gen_range(0..self.board.width()))
+            .collect::<Vec<_>>();
+        self.raise_garbage(&holes);
+    }
+
+    /// Adds the received garbage below the stack. The rows of each attack share
+    /// their hole, so they can be cleared together.
+    fn raise_incoming_garbage(&mut self) {
+        let mut holes = Vec::new();
+        for rows in std::mem::take(&mut self.incoming_garbage) {
+            let hole = self.garbage_rng.gen_range(0..self.board.width());
+            holes.extend(std::iter::repeat_n(hole, rows as usize));
+        }
+        self.raise_garbage(&holes);
+    }
+
+    /// Uses `rows` garbage rows to cancel received garbage, oldest first, and
+    /// sends the rest to the opponent.
+    fn attack(&mut self, mut rows: u32) {
+        for incoming in &mut self.incoming_garbage {
+            let cancelled = rows.min(*incoming);
+            *incoming -= cancelled;
+            rows -= cancelled;
+        }
+        self.incoming_garbage.retain(|&incoming| incoming > 0);
+        self.outgoing_garbage += rows;
+    }
+
+    /// Adds a garbage row below the stack for each hole column, top to bottom.
+    /// The falling tetromino is pushed up if it overlaps the risen stack.
+    fn raise_garbage(&mut self, holes: &[usize]) {
+        for &hole in holes {
+            if !self.board.add_garbage(hole) {
+                self.top_out = Some(TopOut::Overflow);
+            }
+        }
+
+        while !self.board.can_fit(self.falling_tetromino) {
+            let moved = self.falling_tetromino.moved(ivec2(0, -1));
+            if moved.position().y < -(2 * self.board.height() as i32) {
+                self.top_out = Some(TopOut::BlockOut);
+                return;
+            }
+            self.falling_tetromino = moved;
+        }
+    }
+
+    /// Creates the results screen, after adding the game to the session
+    /// statistics.
+    fn game_over(&mut self) -> GameOver {
+        let progress = self.progress();
+        let mut results = self.mode.results(&progress, self.finished);
+        let session = self.statistics.add_to_session();
+        results.text += &format!(
+            "\n\nPerfect clears this session: {}",
+            session.perfect_clears
+        );
+        GameOver::new(self.settings, results, self.seed(), self.replays.clone())
+    }
+
+    /// Checks whether placing the falling tetromino now would be a T-spin, using
+    /// the 3-corner rule.
+    ///
+    /// The last action has to be a rotation and at least 3 of the 4 squares
+    /// diagonal to the T's center have to be blocked. It's a mini T-spin if only
+    /// one of the two corners the T points towards is blocked, unless the
+    /// rotation used the last SRS kick (as in a T-spin triple).
+    fn t_spin(&self) -> Option<TSpin> {
+        /// Index of the last SRS kick, which always makes a full T-spin.
+        const TST_KICK: usize = 4;
+
+        let kick = self.last_rotation_kick?;
+        if self.falling_tetromino.tetromino != Tetromino::T {
+            return None;
+        }
+
+        // The center is the square next to all 3 others, and the T points away
+        // from the side with no neighbor.
+        let squares = self.falling_tetromino.squares();
+        let center = *squares.iter().find(|&&square| {
+            let neighbors = squares.iter().filter(|&&other| {
+                let distance = (other - square).abs();
+                distance.x + distance.y == 1
+            });
+            neighbors.count() == 3
+        })?;
+        let pointing = [ivec2(0, -1), ivec2(1, 0), ivec2(0, 1), ivec2(-1, 0)]
+            .into_iter()
+            .find(|&direction| !squares.contains(&(center - direction)))?;
+
+        let blocked = |corner: IVec2| self.board.is_blocked(center + corner);
+        let side = pointing.perp();
+        let front = [pointing + side, pointing - side];
+        let back = [-pointing + side, -pointing - side];
+        let front_blocked = front.into_iter().filter(|&corner| blocked(corner)).count();
+        let back_blocked = back.into_iter().filter(|&corner| blocked(corner)).count();
+
+        match (front_blocked, back_blocked) {
+            (2, 1..) => Some(TSpin::Full),
+            (1, 2) if kick == TST_KICK => Some(TSpin::Full),
+            (1, 2) => Some(TSpin::Mini),
+            _ => None,
+        }
+    }
+
+    /// Swaps the falling tetromino with the held one, or with the next one if
+    /// nothing is held yet. Can only be used once per tetromino.
+    fn hold(&mut self) {
+        if self.hold_locked {
+            return;
+        }
+        let next = match self
+            .held_tetromino
+            .replace(self.falling_tetromino.tetromino)
+        {
+            Some(held) => held,
+            None => self.queue.generate(&mut self.rng),
+        };
+        self.spawn(next);
+        self.hold_locked = true;
+    }
+
+    /// Returns `tetromino` as it spawns, in the buffer just above the visible
+    /// board. It immediately moves down a row if possible.
+    pub fn spawned(&self, tetromino: Tetromino) -> FallingTetromino {
+        let spawned =
+            FallingTetromino::spawn(tetromino, self.settings.rotation_system, self.board.width())
+                .moved(ivec2(0, -2));
+        let moved = spawned.moved(ivec2(0, 1));
+        if self.board.can_fit(spawned) && self.board.can_fit(moved) {
+            moved
+        } else {
+            spawned
+        }
+    }
+
+    /// Replaces the falling tetromino with a newly spawned one (see
+    /// [`Game::spawned`]). The game is lost if it doesn't fit.
+    fn spawn(&mut self, tetromino: Tetromino) {
+        self.falling_tetromino = self.spawned(tetromino);
+        self.lock_ticks = 0;
+        self.lock_resets = 0;
+        self.touched_down = false;
+        self.last_rotation_kick = None;
 
         if !self.board.can_fit(self.falling_tetromino) {
-            self.lost = true;
+            self.top_out = Some(TopOut::BlockOut);
+            return;
         }
+        self.lowest_row = self.falling_tetromino.position().y;
     }
 
-    /// Moves the falling tetromino down one square. If it can't be moved down it's
-    /// placed.
-    fn fall(&mut self) {
-        if !self.try_move(ivec2(0, 1)) {
+    /// Moves the falling tetromino down according to the gravity of the current
+    /// level. Fast enough gravity moves it several rows in a single tick.
+    fn update_gravity(&mut self) {
+        let soft_dropping = self.held_inputs.contains(&Input::SoftDrop);
+        let mut gravity = gravity(self.level);
+        if soft_dropping {
+            gravity *= self.settings.soft_drop_factor as f32;
+        }
+        // 20G drops to the bottom of boards of any height.
+        if gravity >= TWENTY_G {
+            gravity = f32::INFINITY;
+        }
+
+        self.fall_progress += gravity;
+        while self.fall_progress >= 1.0 {
+            self.fall_progress -= 1.0;
+            if !self.try_move(ivec2(0, 1)) {
+                self.fall_progress = 0.0;
+                break;
+            }
+            if soft_dropping {
+                self.score += Scoring::soft_drop(1);
+            }
+        }
+    }
+
+    /// Advances the lock delay while the falling tetromino rests on the stack and
+    /// places it once the delay runs out.
+    fn update_lock_delay(&mut self) {
+        if !self.is_grounded() {
+            return;
+        }
+        self.touched_down = true;
+        self.lock_ticks += 1;
+
+        let resets_used_up = matches!(
+            self.settings.lock_reset,
+            LockReset::Move { limit } if self.lock_resets >= limit
+        );
+        if self.lock_ticks >= self.settings.lock_delay || resets_used_up {
             self.finalize();
         }
     }
 }
 
+/// A saved game as written to disk. `G` is a [`Game`] or a reference to one.
+#[derive(Serialize, Deserialize)]
+struct SaveFile<G> {
+    /// Version of the file format, so old saves aren't misread.
+    version: u32,
+    game: G,
+    /// See [`GameMode::save_state`].
+    mode: Value,
+    /// See [`Randomizer::save_state`](crate::randomizer::Randomizer::save_state).
+    randomizer: Value,
+}
+
+impl Game {
+    /// The current version of the save file format.
+    const SAVE_VERSION: u32 = 1;
+
+    /// Checks whether there is a saved game to continue.
+    pub fn has_save() -> bool {
+        Archive::saves().latest().is_some()
+    }
+
+    /// Saves the game to continue it later, next to other saved games. Only the
+    /// most recent saves are kept. Held inputs are released first, since their
+    /// keys won't be held anymore when the game is continued.
+    pub fn save(&mut self) -> io::Result<()> {
+        self.release_all();
+        Archive::saves().add(&self.to_save_json())?;
+        Ok(())
+    }
+
+    /// Loads the most recently saved game and deletes its save, so it's only
+    /// continued once. The game saves its replay when it ends, like new games
+    /// from the main menu. A save that can't be read is renamed to end in
+    /// `.unreadable`, so the game before it can be continued instead.
+    pub fn load_save() -> io::Result<Self> {
+        let path = Archive::saves()
+            .latest()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no saved game"))?;
+        match fs::read_to_string(&path).and_then(|json| Self::from_save_json(&json)) {
+            Ok(game) => {
+                fs::remove_file(&path)?;
+                Ok(game.with_replays(Some(Archive::replays())))
+            }
+            Err(err) => {
+                fs::rename(&path, path.with_extension("json.unreadable"))?;
+                Err(err)
+            }
+        }
+    }
+
+    fn to_save_json(&self) -> String {
+        let save = SaveFile {
+            version: Self::SAVE_VERSION,
+            mode: self.mode.save_state(),
+            randomizer: self.queue.save_state(),
+            game: self,
+        };
+        serde_json::to_string(&save).expect("games can always be serialized")
+    }
+
+    /// Parses a saved game, rejecting other versions of the file format, invalid
+    /// settings and boards that don't match them. The mode and randomizer are
+    /// rebuilt from the settings and their saved state.
+    fn from_save_json(json: &str) -> io::Result<Self> {
+        archive::check_version("save", Self::SAVE_VERSION, json)?;
+        let SaveFile {
+            mut game,
+            mode,
+            randomizer,
+            ..
+        } = serde_json::from_str::<SaveFile<Game>>(json)?;
+        game.settings.validate()?;
+        let (width, height) = (game.settings.board_width, game.settings.board_height);
+        if !game.board.has_size(width, height) || !game.board.can_fit(game.falling_tetromino) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("saved board doesn't match a {width}x{height} board"),
+            ));
+        }
+        game.mode = game.settings.mode.build();
+        game.mode.restore_state(mode)?;
+        let mut rebuilt = game.settings.randomizer.build();
+        rebuilt.restore_state(randomizer)?;
+        game.queue.set_randomizer(rebuilt);
+        Ok(game)
+    }
+
+    /// Stands in for the mode of deserialized games.
+    fn unset_mode() -> Box<dyn GameMode> {
+        Box::new(Marathon)
+    }
+}
+
 impl Scene for Game {
     /// Handles keyboard input.
     fn keyboard_input(&mut self, input: KeyboardInput) -> Action {
         match (input.scancode, input.state) {
             // Exit [Esc] (temporary)
-            (1, ElementState::Pressed) => {
-                return Action::Exit;
-            }
-            // Rotate counterclockwise. [Q] / [Z] / [I]
-            (16 | 44 | 23, ElementState::Pressed) => {
-                self.try_rotate(-1);
-            }
-            // Rotate clockwise. [E] / [X] / [P]
-            (18 | 45 | 25, ElementState::Pressed) => {
-                self.try_rotate(1);
+            (1, ElementState::Pressed) => return Action::Exit,
+            // Pause [Enter]
+            (28, ElementState::Pressed) => {
+                self.paused = !self.paused;
+                self.release_all();
+                return Action::Continue;
             }
-            // Move left. [A] / [Left] / [K]
-            (30 | 57419 | 37, ElementState::Pressed) => {
-                self.try_move(ivec2(-1, 0));
-            }
-            // Move right. [D] / [Right] / [;]
-            (32 | 57421 | 39, ElementState::Pressed) => {
-                self.try_move(ivec2(1, 0));
-            }
-            // Move down. [S] / [Down] / [L]
-            (31 | 57424 | 38, ElementState::Pressed) => {
-                self.try_move(ivec2(0, 1));
-            }
-            // Drop. [Space]
-            (57, ElementState::Pressed) => {
-                self.drop();
-            }
-            // TODO Remove once everything else is finished.
-            (scancode, ElementState::Pressed) => println!("{scancode}"),
+            // Save and quit to the main menu while paused [Q]
+            (16, ElementState::Pressed) if self.paused => match self.save() {
+                Ok(()) => {
+                    let settings = Settings {
+                        seed: None,
+                        ..self.settings
+                    };
+                    return Action::SwitchScene(Box::new(MainMenu::new(settings)));
+                }
+                Err(err) => log::error!("couldn't save game: {err}"),
+            },
             _ => (),
         }
+        if self.paused {
+            return Action::Continue;
+        }
+
+        self.key_input(input);
         Action::Continue
     }
 
     /// Updates the game logic. Should be called 60 times per second.
     fn tick(&mut self) -> Action {
-        if self.lost {
-            // TODO Use overlay instead.
-            return Action::SwitchScene(Box::new(GameOver::new(self.score)));
+        if self.paused {
+            return Action::Continue;
         }
-
-        self.ticks_elapsed += 1;
-        if self.ticks_elapsed == 60 {
-            self.ticks_elapsed = 0;
-            self.fall();
+        if self.is_over() {
+            if let Some(replays) = &self.replays {
+                if let Err(err) = self.recording().save(replays) {
+                    log::error!("couldn't save replay: {err}");
+                }
+            }
+            // TODO Use overlay instead.
+            return Action::SwitchScene(Box::new(self.game_over()));
         }
+        self.update();
         Action::Continue
     }
 
-    /// Renders the game.
+    /// Renders the game centered horizontally.
     fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError> {
-        self.board.render(ctx, vec2(20.0, 20.0));
-        self.render_falling(ctx, vec2(25.0, 25.0));
-        self.render_next(ctx, vec2(350.0, 20.0), vec2(210.0, 150.0));
-
-        render_boxed_text(
-            ctx,
-            vec2(350.0, 190.0),
-            vec2(210.0, 80.0),
-            &format!("SCORE\n{}", self.score),
-        );
-        render_boxed_text(
-            ctx,
-            vec2(350.0, 290.0),
-            vec2(210.0, 80.0),
-            &format!("LEVEL\n{}", self.level),
-        );
-        render_boxed_text(
-            ctx,
-            vec2(350.0, 390.0),
-            vec2(210.0, 80.0),
-            &format!("LINES\n{}", self.rows_cleared),
-        );
-
+        let x = ((ctx.config.width as f32 - Self::SIZE.x) / 2.0).max(0.0);
+        self.render_at(ctx, vec2(x, 0.0));
+        if self.paused {
+            self.render_paused(ctx, vec2(x, 0.0) + Self::SIZE / 2.0);
+        }
         ctx.render_frame()
     }
+
+    /// Saves the game to continue it later, unless it's over.
+    fn exit(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        if let Err(err) = self.save() {
+            log::error!("couldn't save game: {err}");
+        }
+    }
 }
 
 impl Game {
+    /// The size of the area a game is rendered in.
+    pub const SIZE: Vec2 = vec2(690.0, 650.0);
+    /// The space for the squares of the board. Squares are shrunk to fit big
+    /// boards in.
+    const BOARD_AREA: Vec2 = vec2(300.0, 600.0);
+    /// The size of squares of all but the first tetromino in the next queue.
+    const SMALL_SQUARE_SIZE: f32 = 20.0;
+    /// How long the most recent line clear is shown.
+    const CLEAR_DISPLAY_TICKS: u32 = 120;
+
+    /// Queues the game for rendering in an area of [`Game::SIZE`] with its top
+    /// left corner at `offset`.
+    pub fn render_at(&self, ctx: &mut RenderContext, offset: Vec2) {
+        self.render_hold(ctx, offset + vec2(20.0, 20.0), vec2(150.0, 150.0));
+        let mut y = 190.0;
+        for panel in self.mode.panels(&self.progress()) {
+            y += self.render_panel(ctx, panel, offset + vec2(20.0, y), 150.0) + 20.0;
+        }
+        self.render_last_clear(ctx, offset + vec2(20.0, y), 150.0);
+
+        // Shrink the squares of big boards so they fit, and center narrow ones.
+        let square_size = (Self::BOARD_AREA
+            / vec2(self.board.width() as f32, self.board.height() as f32))
+        .min_element()
+        .min(TetrominoSquare::SIZE);
+        let board_size = self.board.render_size(square_size);
+        let board_position = offset + vec2(190.0 + (310.0 - board_size.x) / 2.0, 20.0);
+        self.board.render(ctx, board_position, square_size);
+        let squares_position = board_position + Vec2::splat(5.0);
+        if self.settings.ghost_piece {
+            self.render_ghost(ctx, squares_position, square_size);
+        }
+        self.render_falling(ctx, squares_position, square_size);
+        self.render_perfect_clear(ctx, board_position + board_size / 2.0);
+        self.render_garbage_meter(ctx, board_position, board_size, square_size);
+
+        let next_height = 70.0 + 60.0 * self.settings.preview_count as f32;
+        self.render_next(ctx, offset + vec2(520.0, 20.0), vec2(150.0, next_height));
+    }
+
+    /// Renders the received garbage waiting to rise as a red bar left of the
+    /// board, one square high per row.
+    fn render_garbage_meter(
+        &self,
+        ctx: &mut RenderContext,
+        board_position: Vec2,
+        board_size: Vec2,
+        square_size: f32,
+    ) {
+        let rows = self.incoming_garbage().min(self.board.height() as u32);
+        if rows == 0 {
+            return;
+        }
+        let height = rows as f32 * square_size;
+        ctx.quad_renderer.submit(Quad {
+            position: vec2(
+                board_position.x - 15.0,
+                board_position.y + board_size.y - 5.0 - height,
+            ),
+            size: vec2(10.0, height),
+            fill_color: vec4(0.9, 0.2, 0.2, 1.0),
+            border_size: 0.0,
+            border_color: vec4(0.0, 0.0, 0.0, 0.0),
+        });
+    }
+
+    /// Renders a blinking "PERFECT CLEAR" centered on `center` after the board was
+    /// cleared completely.
+    fn render_perfect_clear(&self, ctx: &mut RenderContext, center: Vec2) {
+        let perfect_clear = self.last_clear.is_some_and(|clear| clear.perfect_clear);
+        if !perfect_clear || self.clear_display_ticks == 0 || self.clear_display_ticks / 10 % 2 == 1
+        {
+            return;
+        }
+        ctx.glyph_brush.queue(Section {
+            screen_position: (center.x, center.y),
+            text: vec![Text::new("PERFECT\nCLEAR")
+                .with_color([1.0, 0.85, 0.2, 1.0])
+                .with_scale(50.0)],
+            bounds: (f32::INFINITY, f32::INFINITY),
+            layout: Layout::default_wrap()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Center),
+        });
+    }
+
+    /// Renders the pause menu centered on `center`.
+    fn render_paused(&self, ctx: &mut RenderContext, center: Vec2) {
+        let text = "Enter: resume\nQ: save and quit";
+        ctx.glyph_brush.queue(Section {
+            screen_position: (center.x, center.y),
+            text: vec![
+                Text::new("PAUSED\n")
+                    .with_color([1.0, 1.0, 1.0, 1.0])
+                    .with_scale(50.0),
+                Text::new(text)
+                    .with_color([1.0, 1.0, 1.0, 1.0])
+                    .with_scale(30.0),
+            ],
+            bounds: (f32::INFINITY, f32::INFINITY),
+            layout: Layout::default_wrap()
+                .h_align(HorizontalAlign::Center)
+                .v_align(VerticalAlign::Center),
+        });
+    }
+
+    /// Renders a HUD panel and returns its height.
+    fn render_panel(
+        &self,
+        ctx: &mut RenderContext,
+        panel: Panel,
+        position: Vec2,
+        width: f32,
+    ) -> f32 {
+        match panel {
+            Panel::Value { title, value } => {
+                let size = vec2(width, 80.0);
+                render_boxed_text(ctx, position, size, &format!("{title}\n{value}"));
+                size.y
+            }
+            Panel::List {
+                title,
+                segments,
+                height,
+            } => {
+                render_boxed_text(ctx, position, vec2(width, height), title);
+                ctx.glyph_brush.queue(Section {
+                    screen_position: (position.x + width / 2.0, position.y + 45.0),
+                    text: segments
+                        .iter()
+                        .map(|(text, color)| Text::new(text).with_color(*color).with_scale(18.0))
+                        .collect(),
+                    bounds: (width, f32::INFINITY),
+                    layout: Layout::default_wrap().h_align(HorizontalAlign::Center),
+                });
+                height
+            }
+        }
+    }
+
+    /// Renders the most recent line clear and the points it earned, e.g.
+    /// "TETRIS B2B +1800".
+    fn render_last_clear(&self, ctx: &mut RenderContext, position: Vec2, width: f32) {
+        let Some(last_clear) = self.last_clear.filter(|_| self.clear_display_ticks > 0) else {
+            return;
+        };
+        ctx.glyph_brush.queue(Section {
+            screen_position: (position.x + width / 2.0, position.y),
+            text: vec![Text::new(&last_clear.to_string())
+                .with_color([1.0, 1.0, 1.0, 1.0])
+                .with_scale(24.0)],
+            bounds: (width, f32::INFINITY),
+            layout: Layout::default_wrap().h_align(HorizontalAlign::Center),
+        });
+    }
+
     /// Renders the falling tetromino.
-    fn render_falling(&self, ctx: &mut RenderContext, offset: Vec2) {
+    fn render_falling(&self, ctx: &mut RenderContext, offset: Vec2, square_size: f32) {
         let squares = self.falling_tetromino.squares();
         let instances = squares
             .iter()
             .filter(|pos| pos.y >= 0)
             .map(|&pos| TetrominoSquare {
-                position: offset + pos.as_vec2() * Vec2::splat(TetrominoSquare::SIZE),
+                position: offset + pos.as_vec2() * Vec2::splat(square_size),
                 color: self.falling_tetromino.tetromino.color(),
+                size: square_size,
+                style: TetrominoSquare::SOLID,
             });
         ctx.square_renderer.submit_iter(instances);
     }
 
-    /// Renders the next tetromino.
+    /// Renders the outline of the falling tetromino where it would land.
+    fn render_ghost(&self, ctx: &mut RenderContext, offset: Vec2, square_size: f32) {
+        let ghost = self.board.drop_position(self.falling_tetromino);
+        let instances = ghost
+            .squares()
+            .into_iter()
+            .filter(|pos| pos.y >= 0)
+            .map(|pos| TetrominoSquare {
+                position: offset + pos.as_vec2() * Vec2::splat(square_size),
+                color: ghost.tetromino.color(),
+                size: square_size,
+                style: TetrominoSquare::OUTLINE,
+            });
+        ctx.square_renderer.submit_iter(instances);
+    }
+
+    /// Renders the next tetrominoes as a column, the first one larger than the
+    /// rest.
     fn render_next(&self, ctx: &mut RenderContext, position: Vec2, size: Vec2) {
         render_boxed_text(ctx, position, size, "NEXT");
 
-        let center = vec2(position.x + size.x / 2.0, position.y + 30.0);
+        let mut center = vec2(position.x + size.x / 2.0, position.y + 90.0);
+        for (i, tetromino) in self.queue.peek().enumerate() {
+            let square_size = if i == 0 {
+                TetrominoSquare::SIZE
+            } else {
+                Self::SMALL_SQUARE_SIZE
+            };
+            self.render_centered(ctx, tetromino, center, tetromino.color(), square_size);
+            center.y += if i == 0 { 75.0 } else { 60.0 };
+        }
+    }
 
-        // How many squares to offset the tetromino so that it's centered (-2.0 or -2.5)
-        let offset = -((self.next_tetromino.width(0) % 2) as f32 * 0.5 + 2.0);
+    /// Renders the held tetromino. It's grayed out while hold can't be used.
+    fn render_hold(&self, ctx: &mut RenderContext, position: Vec2, size: Vec2) {
+        render_boxed_text(ctx, position, size, "HOLD");
 
-        let next_squares = self.next_tetromino.squares(0);
-        let instances = next_squares.iter().map(|&pos| TetrominoSquare {
-            position: center
-                + (vec2(offset, 0.0) + pos.as_vec2()) * Vec2::splat(TetrominoSquare::SIZE),
-            color: self.next_tetromino.color(),
+        let Some(held_tetromino) = self.held_tetromino else {
+            return;
+        };
+
+        let center = vec2(position.x + size.x / 2.0, position.y + size.y / 2.0 + 15.0);
+        let color = if self.hold_locked {
+            vec4(0.4, 0.4, 0.4, 1.0)
+        } else {
+            held_tetromino.color()
+        };
+        self.render_centered(ctx, held_tetromino, center, color, TetrominoSquare::SIZE);
+    }
+
+    /// Renders a tetromino in spawn orientation centered on `center`.
+    fn render_centered(
+        &self,
+        ctx: &mut RenderContext,
+        tetromino: Tetromino,
+        center: Vec2,
+        color: Vec4,
+        square_size: f32,
+    ) {
+        // Offset the tetromino by half its bounding box so that it's centered.
+        let squares = self.settings.rotation_system.squares(tetromino, 0);
+        let min = squares
+            .iter()
+            .fold(IVec2::splat(i32::MAX), |acc, &pos| acc.min(pos));
+        let max = squares
+            .iter()
+            .fold(IVec2::splat(i32::MIN), |acc, &pos| acc.max(pos));
+        let offset = -(min + max + IVec2::ONE).as_vec2() / 2.0;
+
+        let instances = squares.iter().map(|&pos| TetrominoSquare {
+            position: center + (offset + pos.as_vec2()) * Vec2::splat(square_size),
+            color,
+            size: square_size,
+            style: TetrominoSquare::SOLID,
         });
         ctx.square_renderer.submit_iter(instances);
     }
@@ -228,18 +1158,637 @@ fn render_boxed_text(ctx: &mut RenderContext, position: Vec2, size: Vec2, text:
     });
 }
 
-/// Calculates the score for a given number of cleared rows.
-///
-/// # Panics
-///
-/// Panics if the number of cleared rows is greater than 4.
-fn calc_score(rows_cleared: u8) -> u32 {
-    match rows_cleared {
-        0 => 0,
-        1 => 40,
-        2 => 100,
-        3 => 300,
-        4 => 1200,
-        _ => panic!("it should not be possible to clear more than 4 rows at once"),
+/// Gravity in cells per tick for levels 1 to 19, following the guideline curve
+/// `(0.8 - (level - 1) * 0.007) ^ (level - 1)` seconds per row. From level 19 on
+/// tetrominoes drop to the bottom instantly (20G).
+const GRAVITY: [f32; 19] = [
+    0.01667, 0.02102, 0.02698, 0.03526, 0.04692, 0.06361, 0.08787, 0.12370, 0.17753, 0.25980,
+    0.38781, 0.59065, 0.91811, 1.45696, 2.36118, 3.90910, 6.61354, 11.43794, TWENTY_G,
+];
+
+/// The highest gravity, named after the height of the standard board. Drops
+/// tetrominoes to the bottom instantly on taller boards too.
+const TWENTY_G: f32 = 20.0;
+
+/// Returns the gravity in cells per tick for a level.
+fn gravity(level: u32) -> f32 {
+    let index = (level.max(1) as usize - 1).min(GRAVITY.len() - 1);
+    GRAVITY[index]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, Weights};
+    use crate::mode::ModeKind;
+    use crate::randomizer::RandomizerKind;
+    use crate::rotation::Srs;
+
+    /// Hard drops `count` tetrominoes and returns their types.
+    fn drop_sequence(game: &mut Game, count: usize) -> Vec<Tetromino> {
+        (0..count)
+            .map(|_| {
+                let tetromino = game.falling_tetromino.tetromino;
+                game.drop();
+                tetromino
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let settings = Settings {
+            seed: Some(42),
+            ..Settings::default()
+        };
+        let mut a = Game::new(settings);
+        let mut b = Game::new(settings);
+        assert_eq!(a.seed(), 42);
+        assert_eq!(drop_sequence(&mut a, 20), drop_sequence(&mut b, 20));
+    }
+
+    #[test]
+    fn different_seed_different_sequence() {
+        let mut a = Game::new(Settings {
+            seed: Some(1),
+            ..Settings::default()
+        });
+        let mut b = Game::new(Settings {
+            seed: Some(2),
+            ..Settings::default()
+        });
+        assert_ne!(drop_sequence(&mut a, 20), drop_sequence(&mut b, 20));
+    }
+
+    #[test]
+    fn garbage_keeps_sequence() {
+        let settings = Settings {
+            seed: Some(4),
+            ..Settings::default()
+        };
+        let mut a = Game::new(settings);
+        let mut b = Game::new(settings);
+        b.receive_garbage(1);
+        a.drop();
+        b.drop();
+        assert_eq!(b.board.garbage_rows(), 1);
+        let a_sequence = (0..14)
+            .map(|_| a.queue.generate(&mut a.rng))
+            .collect::<Vec<_>>();
+        let b_sequence = (0..14)
+            .map(|_| b.queue.generate(&mut b.rng))
+            .collect::<Vec<_>>();
+        assert_eq!(a_sequence, b_sequence);
+    }
+
+    #[test]
+    fn hold_once_per_tetromino() {
+        let mut game = Game::new(Settings {
+            seed: Some(7),
+            ..Settings::default()
+        });
+        let first = game.falling_tetromino.tetromino;
+        let second = game.queue.peek().next().unwrap();
+
+        game.hold();
+        assert_eq!(game.held_tetromino, Some(first));
+        assert_eq!(game.falling_tetromino.tetromino, second);
+
+        // Locked until the tetromino is placed.
+        game.hold();
+        assert_eq!(game.held_tetromino, Some(first));
+        assert_eq!(game.falling_tetromino.tetromino, second);
+
+        game.drop();
+        let third = game.falling_tetromino.tetromino;
+        game.hold();
+        assert_eq!(game.held_tetromino, Some(third));
+        assert_eq!(game.falling_tetromino.tetromino, first);
+    }
+
+    #[test]
+    fn hold_resets_position_and_rotation() {
+        let mut game = Game::new(Settings::default());
+        let spawned = game.falling_tetromino;
+        game.try_move(ivec2(1, 0));
+        game.try_move(ivec2(0, 1));
+        game.try_rotate(1);
+        game.hold();
+        game.drop();
+        game.hold();
+        assert_eq!(game.falling_tetromino.position(), spawned.position());
+        assert_eq!(game.falling_tetromino.rotation(), 0);
+    }
+
+    /// Starts a game with the given lock reset policy and moves the falling
+    /// tetromino to the floor.
+    fn landed_game(lock_reset: LockReset) -> Game {
+        let mut game = Game::new(Settings {
+            seed: Some(3),
+            lock_reset,
+            ..Settings::default()
+        });
+        game.falling_tetromino = game.board.drop_position(game.falling_tetromino);
+        game.reset_lock_delay();
+        game
+    }
+
+    /// Ticks the game `ticks` times and returns whether the falling tetromino was
+    /// placed.
+    fn ticks_until_placed(game: &mut Game, ticks: u32) -> bool {
+        let tetromino = game.falling_tetromino;
+        for _ in 0..ticks {
+            let _ = game.tick();
+        }
+        game.falling_tetromino.position() != tetromino.position()
+    }
+
+    #[test]
+    fn lock_delay() {
+        let mut game = landed_game(LockReset::Step);
+        assert!(!ticks_until_placed(&mut game, 29));
+        assert!(ticks_until_placed(&mut game, 1));
+    }
+
+    #[test]
+    fn move_reset_limit() {
+        let mut game = landed_game(LockReset::Move { limit: 15 });
+        for i in 0..15 {
+            assert!(!ticks_until_placed(&mut game, 20));
+            let by = if i % 2 == 0 { -1 } else { 1 };
+            assert!(game.try_move(ivec2(by, 0)));
+        }
+        // Out of resets: placed as soon as it touches down.
+        assert!(ticks_until_placed(&mut game, 1));
+    }
+
+    #[test]
+    fn resets_counted_after_touching_down() {
+        // Moves right after landing count, before the lock delay ticks.
+        let mut game = landed_game(LockReset::Move { limit: 15 });
+        assert!(game.try_move(ivec2(-1, 0)));
+        assert!(game.try_move(ivec2(1, 0)));
+        assert_eq!(game.lock_resets, 2);
+
+        // An O resting on a ledge it can slide off to the right.
+        let mut game = landed_game(LockReset::Move { limit: 15 });
+        game.board = Board::from_ascii(&["#####     "]);
+        game.falling_tetromino = game.board.drop_position(FallingTetromino::spawn(
+            Tetromino::O,
+            &Srs,
+            Board::DEFAULT_WIDTH,
+        ));
+        game.lowest_row = game.falling_tetromino.position().y;
+        game.lock_resets = 0;
+        assert!(game.is_grounded());
+        assert!(game.try_move(ivec2(1, 0)));
+        assert!(!game.is_grounded());
+        // Moves in the air still count once the tetromino touched down.
+        assert!(game.try_move(ivec2(1, 0)));
+        assert_eq!(game.lock_resets, 2);
+        // Falling below the ledge gives the resets back.
+        assert!(game.try_move(ivec2(0, 1)));
+        assert_eq!(game.lock_resets, 0);
+    }
+
+    #[test]
+    fn step_reset_ignores_moves() {
+        let mut game = landed_game(LockReset::Step);
+        assert!(!ticks_until_placed(&mut game, 20));
+        assert!(game.try_move(ivec2(1, 0)));
+        assert!(ticks_until_placed(&mut game, 10));
+    }
+
+    #[test]
+    fn infinite_reset() {
+        let mut game = landed_game(LockReset::Infinite);
+        for i in 0..100 {
+            assert!(!ticks_until_placed(&mut game, 20));
+            let by = if i % 2 == 0 { -1 } else { 1 };
+            assert!(game.try_move(ivec2(by, 0)));
+        }
+    }
+
+    #[test]
+    fn auto_shift() {
+        let mut game = Game::new(Settings {
+            das: 10,
+            arr: 2,
+            ..Settings::default()
+        });
+        let start = game.falling_tetromino.position().x;
+        let column = |game: &Game| game.falling_tetromino.position().x - start;
+
+        game.press(Input::MoveRight);
+        assert_eq!(column(&game), 1);
+        // Key repeats from the OS are ignored.
+        game.press(Input::MoveRight);
+        assert_eq!(column(&game), 1);
+
+        for _ in 0..9 {
+            let _ = game.tick();
+        }
+        assert_eq!(column(&game), 1);
+        let _ = game.tick();
+        assert_eq!(column(&game), 2);
+        let _ = game.tick();
+        assert_eq!(column(&game), 2);
+        let _ = game.tick();
+        assert_eq!(column(&game), 3);
+
+        game.release(Input::MoveRight);
+        for _ in 0..10 {
+            let _ = game.tick();
+        }
+        assert_eq!(column(&game), 3);
+    }
+
+    #[test]
+    fn instant_auto_repeat() {
+        let mut game = Game::new(Settings {
+            das: 5,
+            arr: 0,
+            ..Settings::default()
+        });
+        game.press(Input::MoveLeft);
+        for _ in 0..5 {
+            let _ = game.tick();
+        }
+        let min_x = game
+            .falling_tetromino
+            .squares()
+            .iter()
+            .map(|square| square.x)
+            .min();
+        assert_eq!(min_x, Some(0));
+    }
+
+    #[test]
+    fn last_pressed_direction_wins() {
+        let mut game = Game::new(Settings {
+            das: 5,
+            arr: 1,
+            ..Settings::default()
+        });
+        let start = game.falling_tetromino.position().x;
+        game.press(Input::MoveLeft);
+        game.press(Input::MoveRight);
+        assert_eq!(game.falling_tetromino.position().x, start);
+        for _ in 0..6 {
+            let _ = game.tick();
+        }
+        assert_eq!(game.falling_tetromino.position().x, start + 2);
+    }
+
+    #[test]
+    fn level_up_every_ten_lines() {
+        let mut game = Game::new(Settings {
+            start_level: 3,
+            ..Settings::default()
+        });
+        assert_eq!(game.level, 3);
+        game.board = Board::from_ascii(&["##### ####"; 4]);
+        game.falling_tetromino = FallingTetromino::spawn(
+            Tetromino::I,
+            game.settings.rotation_system,
+            Board::DEFAULT_WIDTH,
+        )
+        .rotated(1);
+        game.rows_cleared = 6;
+        game.drop();
+        assert_eq!(game.rows_cleared, 10);
+        assert_eq!(game.level, 4);
+    }
+
+    #[test]
+    fn gravity_curve() {
+        assert!(gravity(1) < gravity(2));
+        assert_eq!(gravity(0), gravity(1));
+        assert_eq!(gravity(30), 20.0);
+    }
+
+    #[test]
+    fn twenty_g_drops_instantly() {
+        let mut game = Game::new(Settings {
+            start_level: 20,
+            ..Settings::default()
+        });
+        let _ = game.tick();
+        assert!(game.is_grounded());
+    }
+
+    #[test]
+    fn twenty_g_on_tall_board() {
+        let mut game = Game::new(Settings {
+            start_level: 20,
+            board_width: 20,
+            board_height: 40,
+            ..Settings::default()
+        });
+        let _ = game.tick();
+        assert!(game.is_grounded());
+    }
+
+    #[test]
+    fn hard_drop_points() {
+        let mut game = Game::new(Settings::default());
+        let cells = game
+            .board
+            .drop_position(game.falling_tetromino)
+            .position()
+            .y
+            - game.falling_tetromino.position().y;
+        game.drop();
+        assert_eq!(game.score, 2 * cells as u32);
+    }
+
+    /// Starts a game on `board` with a T placed at `position` and `rotation`, as
+    /// if it was just rotated there using `kick`.
+    fn rotated_t(board: &[&str], position: IVec2, rotation: i8, kick: usize) -> Game {
+        let mut game = Game::new(Settings::default());
+        game.board = Board::from_ascii(board);
+        let spawned = FallingTetromino::spawn(Tetromino::T, &Srs, Board::DEFAULT_WIDTH);
+        game.falling_tetromino = spawned
+            .rotated(rotation)
+            .moved(position - spawned.position());
+        game.last_rotation_kick = Some(kick);
+        game
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let game = rotated_t(
+            &["....#.....", "##...#####", "###.######"],
+            ivec2(2, 17),
+            2,
+            0,
+        );
+        assert_eq!(game.t_spin(), Some(TSpin::Full));
+    }
+
+    #[test]
+    fn mini_t_spin() {
+        let board = ["#.........", "...#######"];
+        let game = rotated_t(&board, ivec2(0, 18), 0, 0);
+        assert_eq!(game.t_spin(), Some(TSpin::Mini));
+
+        // The TST kick always makes a full T-spin.
+        let game = rotated_t(&board, ivec2(0, 18), 0, 4);
+        assert_eq!(game.t_spin(), Some(TSpin::Full));
+    }
+
+    #[test]
+    fn no_t_spin_after_dropping() {
+        let board = ["#.........", "#.........", "#.#......."];
+        let mut game = rotated_t(&board, ivec2(0, 7), 1, 0);
+        game.drop();
+        assert_eq!(game.statistics.t_spins, 0);
+        assert_eq!(game.last_clear, None);
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut game = Game::new(Settings::default());
+        game.board = Board::from_ascii(&["##### ####"; 4]);
+        game.falling_tetromino =
+            FallingTetromino::spawn(Tetromino::I, &Srs, Board::DEFAULT_WIDTH).rotated(1);
+        game.drop();
+        assert!(game.board.is_empty());
+        assert_eq!(game.statistics.perfect_clears, 1);
+        assert!(game.last_clear.unwrap().perfect_clear);
+    }
+
+    #[test]
+    fn spawn_in_buffer() {
+        let mut game = Game::new(Settings::default());
+        game.spawn(Tetromino::T);
+        let squares = game.falling_tetromino.squares();
+        assert!(squares.iter().any(|square| square.y < 0));
+        assert!(squares.iter().any(|square| square.y >= 0));
+    }
+
+    #[test]
+    fn block_out() {
+        let mut game = Game::new(Settings::default());
+        // Spawning above a full board is fine as long as the buffer is free.
+        game.board = Board::from_ascii(&["##########"; Board::DEFAULT_HEIGHT]);
+        game.hold();
+        assert_eq!(game.top_out, None);
+
+        game.board = Board::from_ascii(&["##########"; Board::DEFAULT_HEIGHT + 2]);
+        game.spawn(Tetromino::T);
+        assert_eq!(game.top_out, Some(TopOut::BlockOut));
+    }
+
+    #[test]
+    fn lock_out() {
+        let mut game = Game::new(Settings::default());
+        game.board = Board::from_ascii(&["##########"; Board::DEFAULT_HEIGHT]);
+        game.falling_tetromino = game.falling_tetromino.moved(ivec2(0, -2));
+        game.drop();
+        assert_eq!(game.top_out, Some(TopOut::LockOut));
+    }
+
+    #[test]
+    fn narrow_board() {
+        let mut game = Game::new(Settings {
+            board_width: 4,
+            board_height: 8,
+            ..Settings::default()
+        });
+        assert_eq!(game.board.width(), 4);
+        for _ in 0..20 {
+            assert!(game.board.can_fit(game.falling_tetromino));
+            game.drop();
+            if game.top_out.is_some() {
+                break;
+            }
+        }
+        assert!(game.top_out.is_some());
+    }
+
+    #[test]
+    fn ultra_time_limit() {
+        let mut game = Game::new(Settings {
+            mode: ModeKind::Ultra { seconds: 2 },
+            ..Settings::default()
+        });
+        for _ in 0..119 {
+            let _ = game.tick();
+        }
+        assert!(!game.finished);
+        let _ = game.tick();
+        assert!(game.finished);
+    }
+
+    #[test]
+    fn dig_race() {
+        let mut game = Game::new(Settings {
+            mode: ModeKind::Dig { rows: 3 },
+            ..Settings::default()
+        });
+        assert_eq!(game.board.garbage_rows(), 3);
+        let _ = game.tick();
+        assert!(!game.finished);
+
+        game.board = Board::from_ascii(&["##### ####"]);
+        game.falling_tetromino =
+            FallingTetromino::spawn(Tetromino::I, &Srs, Board::DEFAULT_WIDTH).rotated(1);
+        game.drop();
+        assert_eq!(game.garbage_cleared, 1);
+        game.update();
+        assert!(game.finished);
+    }
+
+    #[test]
+    fn rising_garbage_pushes_falling_tetromino_up() {
+        let mut game = Game::new(Settings::default());
+        game.falling_tetromino = game.board.drop_position(game.falling_tetromino);
+        let position = game.falling_tetromino.position();
+        game.add_garbage(2);
+        assert_eq!(game.falling_tetromino.position(), position - ivec2(0, 2));
+        assert_eq!(game.top_out, None);
+    }
+
+    #[test]
+    fn incoming_garbage_rises_after_placement() {
+        let mut game = Game::new(Settings::default());
+        game.receive_garbage(2);
+        game.receive_garbage(1);
+        game.drop();
+        assert_eq!(game.incoming_garbage(), 0);
+        assert_eq!(game.board.garbage_rows(), 3);
+    }
+
+    #[test]
+    fn line_clears_cancel_incoming_garbage() {
+        let mut game = Game::new(Settings::default());
+        game.receive_garbage(2);
+        game.receive_garbage(3);
+        game.board = Board::from_ascii(&["##### ####"; 4]);
+        game.falling_tetromino =
+            FallingTetromino::spawn(Tetromino::I, &Srs, Board::DEFAULT_WIDTH).rotated(1);
+        // A tetris with a perfect clear sends 14 rows, 5 of them cancelled.
+        game.drop();
+        assert_eq!(game.incoming_garbage(), 0);
+        assert_eq!(game.take_outgoing_garbage(), 9);
+        assert_eq!(game.take_outgoing_garbage(), 0);
+
+        game.receive_garbage(3);
+        game.attack(2);
+        assert_eq!(game.incoming_garbage(), 1);
+        assert_eq!(game.take_outgoing_garbage(), 0);
+    }
+
+    #[test]
+    fn saved_game_continues_the_same_way() {
+        let mut game = Game::new(Settings {
+            seed: Some(3),
+            mode: ModeKind::Sprint { lines: 40 },
+            randomizer: RandomizerKind::SevenBag,
+            ..Settings::default()
+        });
+        let mut bot = Bot::new(Weights::default(), 2);
+        for _ in 0..600 {
+            bot.update(&mut game);
+            game.update();
+        }
+        game.press(Input::SoftDrop);
+        game.release_all();
+
+        let mut continued = Game::from_save_json(&game.to_save_json()).unwrap();
+        assert_eq!(continued.mode.save_state(), game.mode.save_state());
+        let mut bots = [
+            Bot::new(Weights::default(), 2),
+            Bot::new(Weights::default(), 2),
+        ];
+        for _ in 0..600 {
+            bots[0].update(&mut game);
+            game.update();
+            bots[1].update(&mut continued);
+            continued.update();
+        }
+
+        let (progress, continued_progress) = (game.progress(), continued.progress());
+        assert_eq!(continued_progress.score, progress.score);
+        assert_eq!(continued_progress.ticks, progress.ticks);
+        assert_eq!(
+            continued_progress.statistics.pieces,
+            progress.statistics.pieces
+        );
+        assert_eq!(
+            continued.queue().collect::<Vec<_>>(),
+            game.queue().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            continued.falling_tetromino.position(),
+            game.falling_tetromino.position()
+        );
+        assert_eq!(continued.mode.save_state(), game.mode.save_state());
+        assert_eq!(continued.recording().events, game.recording().events);
+    }
+
+    #[test]
+    fn rejects_other_save_versions() {
+        let json = Game::new(Settings::default()).to_save_json();
+        let json = json.replacen(
+            &format!("\"version\":{}", Game::SAVE_VERSION),
+            &format!("\"version\":{}", Game::SAVE_VERSION + 1),
+            1,
+        );
+        assert!(Game::from_save_json(&json).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let mut game = Game::new(Settings::default());
+        game.settings.lines_per_level = 0;
+        assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        game.settings.lines_per_level = 10;
+        game.settings.board_width = 0;
+        assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        game.settings.board_width = 10;
+        game.settings.lock_delay = 0;
+        assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        game.settings.lock_delay = 30;
+        game.settings.arr = u32::MAX;
+        assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        game.settings.arr = 2;
+        game.settings.soft_drop_factor = 0;
+        assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        game.settings.soft_drop_factor = 20;
+        assert!(Game::from_save_json(&game.to_save_json()).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_mode_parameters() {
+        let mut game = Game::new(Settings::default());
+        for mode in [
+            ModeKind::Sprint { lines: 0 },
+            ModeKind::Dig { rows: u32::MAX },
+            ModeKind::Dig { rows: 21 },
+            ModeKind::DigSurvival { seconds: 0 },
+        ] {
+            game.settings.mode = mode;
+            assert!(Game::from_save_json(&game.to_save_json()).is_err());
+        }
+        game.settings.mode = ModeKind::Dig { rows: 20 };
+        assert!(Game::from_save_json(&game.to_save_json()).is_ok());
+    }
+
+    #[test]
+    fn rejects_tampered_boards() {
+        let json = Game::new(Settings::default()).to_save_json();
+        let tamper = |edit: fn(&mut Value)| {
+            let mut save: Value = serde_json::from_str(&json).unwrap();
+            edit(&mut save);
+            Game::from_save_json(&save.to_string())
+        };
+        assert!(tamper(|_| ()).is_ok());
+        assert!(
+            tamper(|save| save["game"]["board"]["grid"]["raw"] = Value::Array(Vec::new())).is_err()
+        );
+        assert!(tamper(|save| save["game"]["board"]["buffer_height"] = Value::from(5)).is_err());
+        assert!(tamper(|save| save["game"]["settings"]["board_width"] = Value::from(6)).is_err());
     }
 }
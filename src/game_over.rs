@@ -3,30 +3,53 @@ use wgpu_glyph::{BuiltInLineBreaker, HorizontalAlign, Layout, Section, Text, Ver
 use winit::event::{ElementState, KeyboardInput};
 
 use crate::{
+    archive::Archive,
     game::Game,
+    mode::Results,
     render::context::RenderContext,
     scene::{Action, Scene},
+    settings::Settings,
 };
 
-// TODO Better game over screen (also show lines and level).
+// TODO Better game over screen.
 pub struct GameOver {
-    score: u32,
+    settings: Settings,
+    results: Results,
+    seed: u64,
+    /// Where the finished game saved its replay, if anywhere. The scene started
+    /// by playing again saves its replay there too.
+    replays: Option<Archive>,
+    /// Creates the scene started by playing again.
+    restart: fn(Settings, Option<Archive>) -> Box<dyn Scene>,
 }
 
 impl GameOver {
-    pub fn new(score: u32) -> Self {
-        Self { score }
+    pub fn new(settings: Settings, results: Results, seed: u64, replays: Option<Archive>) -> Self {
+        Self {
+            settings,
+            results,
+            seed,
+            replays,
+            restart: |settings, replays| Box::new(Game::new(settings).with_replays(replays)),
+        }
+    }
+
+    /// Replaces the scene started by playing again, e.g. for rematches.
+    pub fn with_restart(
+        mut self,
+        restart: fn(Settings, Option<Archive>) -> Box<dyn Scene>,
+    ) -> Self {
+        self.restart = restart;
+        self
     }
 }
 
 impl Scene for GameOver {
     fn keyboard_input(&mut self, input: KeyboardInput) -> Action {
-        match (input.scancode, input.state) {
-            // Start new game [Enter]
-            (28, ElementState::Pressed) => {
-                return Action::SwitchScene(Box::new(Game::new()));
-            }
-            _ => (),
+        // Start new game [Enter]
+        if let (28, ElementState::Pressed) = (input.scancode, input.state) {
+            let restarted = (self.restart)(self.settings, self.replays.take());
+            return Action::SwitchScene(restarted);
         }
         Action::Continue
     }
@@ -41,9 +64,12 @@ impl Scene for GameOver {
                 ctx.config.width as f32 / 2.0,
                 ctx.config.height as f32 / 2.0,
             ),
-            text: vec![Text::new(&format!("Game over!\n\nScore: {}", self.score))
-                .with_color([1.0, 1.0, 1.0, 1.0])
-                .with_scale(30.0)],
+            text: vec![Text::new(&format!(
+                "{}\n\n{}\n\nSeed: {}",
+                self.results.title, self.results.text, self.seed
+            ))
+            .with_color([1.0, 1.0, 1.0, 1.0])
+            .with_scale(30.0)],
             bounds: (ctx.config.width as f32, ctx.config.height as f32),
             layout: Layout::Wrap {
                 line_breaker: BuiltInLineBreaker::default(),
@@ -1,8 +1,12 @@
 use std::{
-    iter::{repeat, Enumerate},
+    iter::{repeat_n, Enumerate},
     slice::Iter,
 };
 
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "RowMajor<T>")]
 pub struct Grid<T> {
     // Row-major representation of the grid.
     raw: Vec<T>,
@@ -30,16 +34,19 @@ impl<T> Grid<T> {
     /// ], 3, 4);
     /// ```
     pub fn from_row_major(values: Vec<T>, width: usize, height: usize) -> Self {
-        assert_eq!(
-            values.len(),
-            width * height,
-            "all rows and columns in a grid must be the same length"
-        );
-        Self {
+        Self::try_from_row_major(values, width, height)
+            .expect("all rows and columns in a grid must be the same length")
+    }
+
+    /// Creates a new `Grid` from a `Vec` of values in row-major order, or returns
+    /// `None` if the provided dimensions don't correspond with the length of the
+    /// values `Vec`.
+    pub fn try_from_row_major(values: Vec<T>, width: usize, height: usize) -> Option<Self> {
+        (values.len() == width * height).then_some(Self {
             raw: values,
             width,
             height,
-        }
+        })
     }
 
     /// Creates a new `Grid` of size `width` by `height` filled with copies of
@@ -49,7 +56,7 @@ impl<T> Grid<T> {
         T: Clone,
     {
         Self {
-            raw: repeat(value).take(width * height).collect(),
+            raw: repeat_n(value, width * height).collect(),
             width,
             height,
         }
@@ -150,7 +157,7 @@ impl<T> Grid<T> {
     /// assert_eq!(iter.next(), Some((1, 1, &"D")));
     /// assert_eq!(iter.next(), None);
     /// ```
-    pub fn iter_with_indices(&self) -> IterWithIndices<T> {
+    pub fn iter_with_indices(&self) -> IterWithIndices<'_, T> {
         IterWithIndices {
             grid: self,
             iter: self.raw.iter().enumerate(),
@@ -163,6 +170,23 @@ impl<T> Grid<T> {
     }
 }
 
+/// A deserialized [`Grid`] whose dimensions aren't checked yet.
+#[derive(Deserialize)]
+struct RowMajor<T> {
+    raw: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+impl<T> TryFrom<RowMajor<T>> for Grid<T> {
+    type Error = &'static str;
+
+    fn try_from(grid: RowMajor<T>) -> Result<Self, Self::Error> {
+        Self::try_from_row_major(grid.raw, grid.width, grid.height)
+            .ok_or("all rows and columns in a grid must be the same length")
+    }
+}
+
 /// Iterator over all [`Grid`] values with `x` and `y` indices.
 pub struct IterWithIndices<'a, T> {
     grid: &'a Grid<T>,
@@ -210,6 +234,12 @@ mod tests {
         ], 2, 2);
     }
 
+    #[test]
+    fn deserialize_bad() {
+        let json = r#"{"raw":["A","B","C"],"width":2,"height":2}"#;
+        assert!(serde_json::from_str::<Grid<String>>(json).is_err());
+    }
+
     #[test]
     fn iter_with_indices() {
         let grid = Grid::from_row_major(vec!["A", "B", "C", "D"], 2, 2);
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+/// An action the player can perform in a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateClockwise,
+    RotateCounterclockwise,
+    Hold,
+}
+
+/// Maps keyboard scancodes to inputs.
+pub struct KeyMap {
+    bindings: Vec<(u32, Input)>,
+}
+
+impl KeyMap {
+    /// Creates a key map from `(scancode, input)` pairs.
+    pub fn new(bindings: Vec<(u32, Input)>) -> Self {
+        Self { bindings }
+    }
+
+    /// Returns the input bound to `scancode`, if any.
+    pub fn input(&self, scancode: u32) -> Option<Input> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == scancode)
+            .map(|&(_, input)| input)
+    }
+
+    /// Returns the bindings of the left player in versus, on the left side of
+    /// the keyboard.
+    pub fn player_one() -> Self {
+        Self::new(vec![
+            // [Q]
+            (16, Input::RotateCounterclockwise),
+            // [E]
+            (18, Input::RotateClockwise),
+            // [A]
+            (30, Input::MoveLeft),
+            // [D]
+            (32, Input::MoveRight),
+            // [S]
+            (31, Input::SoftDrop),
+            // [W]
+            (17, Input::HardDrop),
+            // [Left Shift]
+            (42, Input::Hold),
+        ])
+    }
+
+    /// Returns the bindings of the right player in versus, around the arrow
+    /// keys.
+    pub fn player_two() -> Self {
+        Self::new(vec![
+            // [,]
+            (51, Input::RotateCounterclockwise),
+            // [.]
+            (52, Input::RotateClockwise),
+            // [Left]
+            (57419, Input::MoveLeft),
+            // [Right]
+            (57421, Input::MoveRight),
+            // [Down]
+            (57424, Input::SoftDrop),
+            // [Up]
+            (57416, Input::HardDrop),
+            // [Right Shift]
+            (54, Input::Hold),
+        ])
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new(vec![
+            // [Q] / [Z] / [I]
+            (16, Input::RotateCounterclockwise),
+            (44, Input::RotateCounterclockwise),
+            (23, Input::RotateCounterclockwise),
+            // [E] / [X] / [P]
+            (18, Input::RotateClockwise),
+            (45, Input::RotateClockwise),
+            (25, Input::RotateClockwise),
+            // [A] / [Left] / [K]
+            (30, Input::MoveLeft),
+            (57419, Input::MoveLeft),
+            (37, Input::MoveLeft),
+            // [D] / [Right] / [;]
+            (32, Input::MoveRight),
+            (57421, Input::MoveRight),
+            (39, Input::MoveRight),
+            // [S] / [Down] / [L]
+            (31, Input::SoftDrop),
+            (57424, Input::SoftDrop),
+            (38, Input::SoftDrop),
+            // [Space]
+            (57, Input::HardDrop),
+            // [C] / [Shift]
+            (46, Input::Hold),
+            (42, Input::Hold),
+            (54, Input::Hold),
+        ])
+    }
+}
@@ -1,8 +1,12 @@
-use std::time::Instant;
+use std::{path::Path, time::Instant};
 
+use archive::Archive;
 use main_menu::MainMenu;
 use render::context::RenderContext;
+use replay::{Recording, Replay};
 use scene::{Action, Scene};
+use settings::Settings;
+use tbp::{TbpBot, TbpGame};
 use winit::{
     dpi::PhysicalSize,
     event::*,
@@ -10,25 +14,77 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+mod archive;
 mod board;
+mod bot;
 mod game;
 mod game_over;
 #[allow(unused)]
 mod grid;
+mod input;
 mod main_menu;
+mod mode;
+mod movegen;
+mod randomizer;
+mod records;
 mod render;
+mod replay;
+mod rotation;
 mod scene;
+mod scoring;
+mod settings;
+mod statistics;
+mod tbp;
 mod tetromino;
+mod versus;
 
 fn main() {
     env_logger::init();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
-        .with_inner_size(PhysicalSize::new(580, 650))
+        .with_inner_size(PhysicalSize::new(1380, 650))
         .build(&event_loop)
         .unwrap();
 
-    let mut run_loop = RunLoop::new(window);
+    let mut settings = Settings::default();
+    // Fixed seed for reproducing games. [--seed <n>]
+    let args = std::env::args().collect::<Vec<_>>();
+    if let Some(i) = args.iter().position(|arg| arg == "--seed") {
+        settings.seed = Some(
+            args.get(i + 1)
+                .and_then(|seed| seed.parse().ok())
+                .expect("--seed must be followed by a number"),
+        );
+    }
+
+    // Let an external bot play. [--tbp <command>]
+    let scene: Box<dyn Scene> = match args.iter().position(|arg| arg == "--tbp") {
+        Some(i) => {
+            let command = args
+                .get(i + 1)
+                .expect("--tbp must be followed by a command");
+            let mut parts = command.split_whitespace();
+            let program = parts.next().expect("--tbp command is empty");
+            let bot =
+                TbpBot::spawn(program, &parts.collect::<Vec<_>>()).expect("couldn't start bot");
+            let game = TbpGame::new(settings, bot).expect("couldn't start bot game");
+            Box::new(game.with_replays(Some(Archive::replays())))
+        }
+        None => Box::new(MainMenu::new(settings)),
+    };
+    // Watch a replay file. [--replay <path>]
+    let scene = match args.iter().position(|arg| arg == "--replay") {
+        Some(i) => {
+            let path = args
+                .get(i + 1)
+                .expect("--replay must be followed by a path");
+            let recording = Recording::load(Path::new(path)).expect("couldn't load replay");
+            Box::new(Replay::new(recording))
+        }
+        None => scene,
+    };
+
+    let mut run_loop = RunLoop::new(window, scene);
 
     event_loop.run(move |event, _, control_flow| {
         run_loop.handle_event(event, control_flow);
@@ -46,12 +102,12 @@ struct RunLoop {
 }
 
 impl RunLoop {
-    fn new(window: Window) -> Self {
+    fn new(window: Window, scene: Box<dyn Scene>) -> Self {
         let render_context = pollster::block_on(RenderContext::new(&window));
         Self {
             window,
             render_context,
-            scene: Box::new(MainMenu::new()),
+            scene,
             start_time: Instant::now(),
             frames: 0,
         }
@@ -86,7 +142,7 @@ impl RunLoop {
 
     fn handle_window_event(&mut self, event: &WindowEvent, control_flow: &mut ControlFlow) {
         match event {
-            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+            WindowEvent::CloseRequested => self.exit(control_flow),
             WindowEvent::Resized(new_size) => {
                 self.render_context.resize(*new_size);
             }
@@ -105,7 +161,12 @@ impl RunLoop {
         match action {
             Action::Continue => (),
             Action::SwitchScene(scene) => self.scene = scene,
-            Action::Exit => *control_flow = ControlFlow::Exit,
+            Action::Exit => self.exit(control_flow),
         }
     }
+
+    fn exit(&mut self, control_flow: &mut ControlFlow) {
+        self.scene.exit();
+        *control_flow = ControlFlow::Exit;
+    }
 }
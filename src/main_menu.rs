@@ -3,17 +3,54 @@ use wgpu_glyph::{BuiltInLineBreaker, HorizontalAlign, Layout, Section, Text, Ver
 use winit::event::{ElementState, KeyboardInput};
 
 use crate::{
+    archive::Archive,
     game::Game,
+    mode::ModeKind,
+    randomizer::RandomizerKind,
     render::context::RenderContext,
+    replay::{Recording, Replay},
+    rotation::ROTATION_SYSTEMS,
     scene::{Action, Scene},
+    settings::{LockReset, Settings},
+    versus::Versus,
 };
 
+/// Board sizes (width, height) to pick from.
+const BOARD_SIZES: [(usize, usize); 4] = [(10, 20), (4, 20), (6, 20), (20, 40)];
+
+/// Delayed auto shift values to pick from, in ticks.
+const DAS_VALUES: [u32; 5] = [6, 8, 10, 12, 16];
+
+/// Auto repeat rates to pick from, in ticks.
+const ARR_VALUES: [u32; 5] = [0, 1, 2, 3, 5];
+
+/// Soft drop factors to pick from.
+const SOFT_DROP_FACTORS: [u32; 4] = [5, 10, 20, 40];
+
+/// Returns the value after `current` in `values`, starting over after the last
+/// one. Values that aren't in `values` are followed by the first one.
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T) -> T {
+    let next = values
+        .iter()
+        .position(|&value| value == current)
+        .map_or(0, |index| index + 1);
+    values[next % values.len()]
+}
+
 // TODO Better main menu.
-pub struct MainMenu {}
+pub struct MainMenu {
+    settings: Settings,
+    /// Whether there is a saved game to continue. The most recently saved one
+    /// is continued first.
+    has_save: bool,
+}
 
 impl MainMenu {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            has_save: Game::has_save(),
+        }
     }
 }
 
@@ -22,7 +59,92 @@ impl Scene for MainMenu {
         match (input.scancode, input.state) {
             // Start game [Enter]
             (28, ElementState::Pressed) => {
-                return Action::SwitchScene(Box::new(Game::new()));
+                return Action::SwitchScene(Box::new(
+                    Game::new(self.settings).with_replays(Some(Archive::replays())),
+                ));
+            }
+            // Start two-player versus [2]
+            (3, ElementState::Pressed) => {
+                return Action::SwitchScene(Box::new(
+                    Versus::new(self.settings).with_replays(Some(Archive::replays())),
+                ));
+            }
+            // Start versus against the bot [3]
+            (4, ElementState::Pressed) => {
+                return Action::SwitchScene(Box::new(
+                    Versus::against_bot(self.settings).with_replays(Some(Archive::replays())),
+                ));
+            }
+            // Continue the last saved game [C]
+            (46, ElementState::Pressed) if self.has_save => match Game::load_save() {
+                Ok(game) => return Action::SwitchScene(Box::new(game)),
+                Err(err) => {
+                    log::error!("couldn't load saved game: {err}");
+                    self.has_save = Game::has_save();
+                }
+            },
+            // Watch the latest replay [P]
+            (25, ElementState::Pressed) => {
+                let recording = Archive::replays()
+                    .latest()
+                    .map(|path| Recording::load(&path));
+                match recording {
+                    Some(Ok(recording)) => {
+                        return Action::SwitchScene(Box::new(Replay::new(recording)))
+                    }
+                    Some(Err(err)) => log::error!("couldn't load replay: {err}"),
+                    None => log::info!("no replay saved yet"),
+                }
+            }
+            // Change mode [M]
+            (50, ElementState::Pressed) => {
+                self.settings.mode = cycle(&ModeKind::VARIANTS, self.settings.mode)
+            }
+            // Change rotation system [R]
+            (19, ElementState::Pressed) => {
+                self.settings.rotation_system =
+                    cycle(&ROTATION_SYSTEMS, self.settings.rotation_system);
+            }
+            // Change randomizer [B]
+            (48, ElementState::Pressed) => {
+                self.settings.randomizer =
+                    cycle(&RandomizerKind::VARIANTS, self.settings.randomizer);
+            }
+            // Change number of previewed tetrominoes [N]
+            (49, ElementState::Pressed) => {
+                self.settings.preview_count =
+                    self.settings.preview_count % Settings::MAX_PREVIEW_COUNT + 1;
+            }
+            // Change lock reset policy [L]
+            (38, ElementState::Pressed) => {
+                self.settings.lock_reset = cycle(&LockReset::VARIANTS, self.settings.lock_reset);
+            }
+            // Change delayed auto shift [D]
+            (32, ElementState::Pressed) => {
+                self.settings.das = cycle(&DAS_VALUES, self.settings.das)
+            }
+            // Change auto repeat rate [A]
+            (30, ElementState::Pressed) => {
+                self.settings.arr = cycle(&ARR_VALUES, self.settings.arr)
+            }
+            // Change soft drop factor [S]
+            (31, ElementState::Pressed) => {
+                self.settings.soft_drop_factor =
+                    cycle(&SOFT_DROP_FACTORS, self.settings.soft_drop_factor);
+            }
+            // Change start level [V]
+            (47, ElementState::Pressed) => {
+                self.settings.start_level =
+                    self.settings.start_level % Settings::MAX_START_LEVEL + 1;
+            }
+            // Change board size [W]
+            (17, ElementState::Pressed) => {
+                let size = (self.settings.board_width, self.settings.board_height);
+                (self.settings.board_width, self.settings.board_height) = cycle(&BOARD_SIZES, size);
+            }
+            // Toggle ghost piece [G]
+            (34, ElementState::Pressed) => {
+                self.settings.ghost_piece = !self.settings.ghost_piece;
             }
             _ => (),
         }
@@ -34,15 +156,43 @@ impl Scene for MainMenu {
     }
 
     fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError> {
-        const TEXT: &str = "Press Enter to start.\n\nUse arrow keys to move left and right. \
-        X and Y to rotate. Spacebar to drop.";
+        let text = format!(
+            "{}Press Enter to start, 2 for two-player versus or 3 for versus against the bot.\n\
+            P to watch the latest replay.\n\nUse arrow keys to move left and right. \
+            Z and X to rotate. Spacebar to drop. C or Shift to hold. Enter to pause.\nVersus: WASD, Q/E and left Shift against \
+            arrow keys, comma/period and right Shift.\n\nMode: {} (M to change)\nRotation system: {} (R to change)\n\
+            Randomizer: {} (B to change)\nPreview: {} (N to change)\nGhost piece: {} (G to change)\n\
+            Lock delay: {} (L to change)\nDAS: {} ticks (D to change)\nARR: {} ticks (A to change)\n\
+            Soft drop: {}x (S to change)\nStart level: {} (V to change)\nBoard: {}x{} (W to change)",
+            if self.has_save {
+                "Press C to continue the last saved game.\n"
+            } else {
+                ""
+            },
+            self.settings.mode.name(),
+            self.settings.rotation_system.name(),
+            self.settings.randomizer.name(),
+            self.settings.preview_count,
+            if self.settings.ghost_piece {
+                "on"
+            } else {
+                "off"
+            },
+            self.settings.lock_reset.name(),
+            self.settings.das,
+            self.settings.arr,
+            self.settings.soft_drop_factor,
+            self.settings.start_level,
+            self.settings.board_width,
+            self.settings.board_height,
+        );
 
         ctx.glyph_brush.queue(Section {
             screen_position: (
                 ctx.config.width as f32 / 2.0,
                 ctx.config.height as f32 / 2.0,
             ),
-            text: vec![Text::new(TEXT)
+            text: vec![Text::new(&text)
                 .with_color([1.0, 1.0, 1.0, 1.0])
                 .with_scale(30.0)],
             bounds: (ctx.config.width as f32, ctx.config.height as f32),
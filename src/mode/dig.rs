@@ -0,0 +1,137 @@
+use super::{format_time, GameMode, Panel, Progress, Results};
+
+/// Dig through garbage rows, each with a hole in a random column.
+///
+/// In a race the board starts with some garbage rows and the game ends once
+/// they're all cleared. In survival, garbage keeps rising from the bottom on a
+/// timer until the stack tops out.
+pub struct Dig {
+    /// Number of garbage rows the board starts with.
+    rows: u32,
+    /// Ticks between rising garbage rows, in survival.
+    rise_interval: Option<u64>,
+}
+
+impl Dig {
+    /// Creates a race to clear `rows` garbage rows.
+    pub fn race(rows: u32) -> Self {
+        Self {
+            rows,
+            rise_interval: None,
+        }
+    }
+
+    /// Creates a survival game starting with `rows` garbage rows, where another
+    /// row rises every `rise_interval` ticks.
+    pub fn survival(rows: u32, rise_interval: u64) -> Self {
+        Self {
+            rows,
+            rise_interval: Some(rise_interval),
+        }
+    }
+}
+
+impl GameMode for Dig {
+    fn update(&mut self, progress: &Progress) -> bool {
+        self.rise_interval.is_none() && progress.garbage_rows == 0
+    }
+
+    fn initial_garbage(&self) -> u32 {
+        self.rows
+    }
+
+    fn rising_garbage(&mut self, progress: &Progress) -> u32 {
+        match self.rise_interval {
+            Some(interval) if progress.ticks > 0 && progress.ticks.is_multiple_of(interval) => 1,
+            _ => 0,
+        }
+    }
+
+    fn panels(&self, progress: &Progress) -> Vec<Panel> {
+        let time = Panel::Value {
+            title: "TIME",
+            value: format_time(progress.time()),
+        };
+        if self.rise_interval.is_some() {
+            return vec![
+                time,
+                Panel::Value {
+                    title: "GARBAGE",
+                    value: progress.garbage_cleared.to_string(),
+                },
+                Panel::Value {
+                    title: "LINES",
+                    value: progress.lines.to_string(),
+                },
+            ];
+        }
+        vec![
+            time,
+            Panel::Value {
+                title: "GARBAGE",
+                value: format!("{}/{}", progress.garbage_rows, self.rows),
+            },
+            Panel::Value {
+                title: "PIECES",
+                value: progress.statistics.pieces.to_string(),
+            },
+        ]
+    }
+
+    fn results(&mut self, progress: &Progress, completed: bool) -> Results {
+        let pieces = progress.statistics.pieces;
+        if completed {
+            return Results {
+                title: "Finished!",
+                text: format!("Time: {}\n\nPieces: {pieces}", format_time(progress.time())),
+            };
+        }
+        if self.rise_interval.is_none() {
+            return progress.game_over();
+        }
+        Results {
+            title: "Game over!",
+            text: format!(
+                "Survived: {}\n\nGarbage cleared: {}\nLines: {}\nPieces: {pieces}",
+                format_time(progress.time()),
+                progress.garbage_cleared,
+                progress.lines,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::Statistics;
+
+    fn progress(ticks: u64, garbage_rows: u32) -> Progress {
+        Progress {
+            score: 0,
+            level: 1,
+            lines: 0,
+            ticks,
+            garbage_rows,
+            garbage_cleared: 0,
+            statistics: Statistics::default(),
+        }
+    }
+
+    #[test]
+    fn race_ends_when_dug_out() {
+        let mut dig = Dig::race(5);
+        assert!(!dig.update(&progress(100, 1)));
+        assert!(dig.update(&progress(100, 0)));
+        assert_eq!(dig.rising_garbage(&progress(180, 0)), 0);
+    }
+
+    #[test]
+    fn survival_rises_on_timer() {
+        let mut dig = Dig::survival(4, 180);
+        assert_eq!(dig.rising_garbage(&progress(0, 4)), 0);
+        assert_eq!(dig.rising_garbage(&progress(179, 4)), 0);
+        assert_eq!(dig.rising_garbage(&progress(180, 4)), 1);
+        assert!(!dig.update(&progress(200, 0)));
+    }
+}
@@ -0,0 +1,32 @@
+use super::{GameMode, Panel, Progress, Results};
+
+/// Endless mode: the game goes on until the stack tops out, while the level
+/// rises.
+pub struct Marathon;
+
+impl GameMode for Marathon {
+    fn update(&mut self, _progress: &Progress) -> bool {
+        false
+    }
+
+    fn panels(&self, progress: &Progress) -> Vec<Panel> {
+        vec![
+            Panel::Value {
+                title: "SCORE",
+                value: progress.score.to_string(),
+            },
+            Panel::Value {
+                title: "LEVEL",
+                value: progress.level.to_string(),
+            },
+            Panel::Value {
+                title: "LINES",
+                value: progress.lines.to_string(),
+            },
+        ]
+    }
+
+    fn results(&mut self, progress: &Progress, _completed: bool) -> Results {
+        progress.game_over()
+    }
+}
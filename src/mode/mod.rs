@@ -0,0 +1,202 @@
+//! Game modes decide when a game ends, which panels the HUD shows and what the
+//! results screen reports, on top of the mechanics in [`Game`](crate::game::Game).
+
+mod dig;
+mod marathon;
+mod sprint;
+mod ultra;
+
+pub use dig::Dig;
+pub use marathon::Marathon;
+pub use sprint::Sprint;
+pub use ultra::Ultra;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::statistics::Statistics;
+
+/// The rules of a game.
+pub trait GameMode {
+    /// Updates goal tracking, called every tick and after every placement.
+    /// Returns whether the goal was reached, which ends the game.
+    fn update(&mut self, progress: &Progress) -> bool;
+
+    /// Returns the number of garbage rows the board starts with.
+    fn initial_garbage(&self) -> u32 {
+        0
+    }
+
+    /// Returns the number of garbage rows to add below the stack now, called
+    /// every tick.
+    fn rising_garbage(&mut self, _progress: &Progress) -> u32 {
+        0
+    }
+
+    /// Returns the panels shown in the HUD below the held tetromino, top to
+    /// bottom.
+    fn panels(&self, progress: &Progress) -> Vec<Panel>;
+
+    /// Returns what's shown on the results screen. `completed` is whether the
+    /// goal was reached, rather than the game being lost.
+    fn results(&mut self, progress: &Progress, completed: bool) -> Results;
+
+    /// Returns the state of the mode that can't be rebuilt from its
+    /// [`ModeKind`], for saving games.
+    fn save_state(&self) -> Value {
+        Value::Null
+    }
+
+    /// Restores a state returned by [`GameMode::save_state`].
+    fn restore_state(&mut self, _state: Value) -> serde_json::Result<()> {
+        Ok(())
+    }
+}
+
+/// The built-in game modes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModeKind {
+    /// Endless: play until topping out while the level rises.
+    Marathon,
+    /// Clear `lines` lines as fast as possible.
+    Sprint { lines: u32 },
+    /// Score as many points as possible in `seconds` seconds.
+    Ultra { seconds: u32 },
+    /// Clear `rows` garbage rows as fast as possible.
+    Dig { rows: u32 },
+    /// Survive while a garbage row rises every `seconds` seconds.
+    DigSurvival { seconds: u32 },
+}
+
+impl ModeKind {
+    /// Number of garbage rows a dig survival game starts with.
+    const SURVIVAL_ROWS: u32 = 4;
+
+    pub const VARIANTS: [ModeKind; 5] = [
+        ModeKind::Marathon,
+        ModeKind::Sprint { lines: 40 },
+        ModeKind::Ultra { seconds: 120 },
+        ModeKind::Dig { rows: 10 },
+        ModeKind::DigSurvival { seconds: 3 },
+    ];
+
+    /// Returns the name of this mode.
+    pub fn name(self) -> String {
+        match self {
+            ModeKind::Marathon => "Marathon".to_string(),
+            ModeKind::Sprint { lines } => format!("Sprint {lines}L"),
+            ModeKind::Ultra { seconds } => {
+                format!("Ultra {}:{:02}", seconds / 60, seconds % 60)
+            }
+            ModeKind::Dig { rows } => format!("Dig {rows}"),
+            ModeKind::DigSurvival { .. } => "Dig survival".to_string(),
+        }
+    }
+
+    /// Creates a new mode of this kind.
+    pub fn build(self) -> Box<dyn GameMode> {
+        match self {
+            ModeKind::Marathon => Box::new(Marathon),
+            ModeKind::Sprint { lines } => Box::new(Sprint::new(lines)),
+            ModeKind::Ultra { seconds } => Box::new(Ultra::new(seconds)),
+            ModeKind::Dig { rows } => Box::new(Dig::race(rows)),
+            ModeKind::DigSurvival { seconds } => {
+                Box::new(Dig::survival(Self::SURVIVAL_ROWS, seconds as u64 * 60))
+            }
+        }
+    }
+}
+
+/// What happened in a game so far, for modes to track their goals.
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub score: u32,
+    pub level: u32,
+    /// Number of lines cleared.
+    pub lines: u32,
+    /// Ticks played, not counting after the game ended.
+    pub ticks: u64,
+    /// Number of rows on the board that contain garbage.
+    pub garbage_rows: u32,
+    /// Number of garbage rows cleared.
+    pub garbage_cleared: u32,
+    pub statistics: Statistics,
+}
+
+impl Progress {
+    /// Returns the time played in milliseconds.
+    pub fn time(&self) -> u64 {
+        self.ticks * 1000 / 60
+    }
+
+    /// Returns the results of a game that was lost or has no goal.
+    pub fn game_over(&self) -> Results {
+        let statistics = &self.statistics;
+        Results {
+            title: "Game over!",
+            text: format!(
+                "Score: {}\n\nPieces: {}\nTetrises: {}\nT-spins: {}\n\
+                Perfect clears: {}\nMax combo: {}",
+                self.score,
+                statistics.pieces,
+                statistics.tetrises,
+                statistics.t_spins,
+                statistics.perfect_clears,
+                statistics.max_combo,
+            ),
+        }
+    }
+}
+
+/// A box in the HUD.
+pub enum Panel {
+    /// A title with a value below, like "SCORE" and "1200".
+    Value { title: &'static str, value: String },
+    /// A title with smaller text below, made of colored segments.
+    List {
+        title: &'static str,
+        segments: Vec<(String, [f32; 4])>,
+        height: f32,
+    },
+}
+
+/// What's shown on the results screen after a game.
+pub struct Results {
+    pub title: &'static str,
+    /// Mode-specific results, one per line.
+    pub text: String,
+}
+
+/// Formats a duration in milliseconds like "1:23.456".
+pub fn format_time(millis: u64) -> String {
+    format!(
+        "{}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Formats the difference between two durations in milliseconds like "+1.234"
+/// or "-0.500".
+pub fn format_time_difference(millis: u64, compared_to: u64) -> String {
+    let (sign, difference) = if millis >= compared_to {
+        ('+', millis - compared_to)
+    } else {
+        ('-', compared_to - millis)
+    };
+    format!("{sign}{}.{:03}", difference / 1000, difference % 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_formatting() {
+        assert_eq!(format_time(83_456), "1:23.456");
+        assert_eq!(format_time(5_007), "0:05.007");
+        assert_eq!(format_time_difference(1_500, 1_000), "+0.500");
+        assert_eq!(format_time_difference(1_000, 2_234), "-1.234");
+    }
+}
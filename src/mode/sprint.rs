@@ -0,0 +1,162 @@
+use std::cell::OnceCell;
+
+use serde_json::{json, Value};
+
+use super::{format_time, format_time_difference, GameMode, Panel, Progress, Results};
+use crate::records;
+
+/// Clear a number of lines as fast as possible, with split times compared to
+/// the personal best.
+pub struct Sprint {
+    /// The number of lines to clear.
+    lines: u32,
+    /// Times (in milliseconds) at which every [`Sprint::SPLIT_LINES`] lines were
+    /// cleared, including the final time.
+    splits: Vec<u64>,
+    /// The splits of the fastest sprint so far, to compare against. Loaded
+    /// when it's first shown, so creating a sprint doesn't read any files.
+    personal_best: OnceCell<Option<Vec<u64>>>,
+}
+
+impl Sprint {
+    /// Number of lines between split times.
+    const SPLIT_LINES: u32 = 10;
+
+    /// Creates a sprint of `lines` lines.
+    pub fn new(lines: u32) -> Self {
+        Self {
+            lines,
+            splits: Vec::new(),
+            personal_best: OnceCell::new(),
+        }
+    }
+
+    /// Returns the splits of the fastest sprint of this many lines, loading them
+    /// the first time.
+    fn personal_best(&self) -> Option<&Vec<u64>> {
+        self.personal_best
+            .get_or_init(|| records::load_sprint(self.lines))
+            .as_ref()
+    }
+}
+
+impl GameMode for Sprint {
+    fn update(&mut self, progress: &Progress) -> bool {
+        let split_count = self.lines.div_ceil(Self::SPLIT_LINES) as usize;
+        while self.splits.len() < split_count {
+            let split_lines = (self.splits.len() as u32 + 1) * Self::SPLIT_LINES;
+            if progress.lines < split_lines.min(self.lines) {
+                break;
+            }
+            self.splits.push(progress.time());
+        }
+        progress.lines >= self.lines
+    }
+
+    fn panels(&self, progress: &Progress) -> Vec<Panel> {
+        // Each split is green when ahead of the personal best and red when behind.
+        let mut segments = Vec::new();
+        for (i, &split) in self.splits.iter().enumerate() {
+            segments.push((format_time(split), [1.0, 1.0, 1.0, 1.0]));
+            if let Some(&best) = self.personal_best().and_then(|best| best.get(i)) {
+                let color = if split < best {
+                    [0.3, 1.0, 0.3, 1.0]
+                } else {
+                    [1.0, 0.3, 0.3, 1.0]
+                };
+                segments.push((format!(" {}", format_time_difference(split, best)), color));
+            }
+            segments.push(("\n".to_string(), [1.0, 1.0, 1.0, 1.0]));
+        }
+
+        vec![
+            Panel::Value {
+                title: "TIME",
+                value: format_time(progress.time()),
+            },
+            Panel::Value {
+                title: "LINES",
+                value: format!("{}/{}", progress.lines, self.lines),
+            },
+            Panel::List {
+                title: "SPLITS",
+                segments,
+                height: 150.0,
+            },
+        ]
+    }
+
+    /// Saves the splits if they're a new personal best.
+    fn results(&mut self, progress: &Progress, completed: bool) -> Results {
+        if !completed {
+            return progress.game_over();
+        }
+
+        let time = progress.time();
+        let new_best = self
+            .personal_best()
+            .and_then(|best| best.last())
+            .is_none_or(|&best| time < best);
+        if new_best {
+            if let Err(err) = records::save_sprint(self.lines, &self.splits) {
+                log::error!("couldn't save personal best: {err}");
+            }
+        }
+
+        let pieces = progress.statistics.pieces;
+        let pps = pieces as f64 / (time.max(1) as f64 / 1000.0);
+        Results {
+            title: "Finished!",
+            text: format!(
+                "Time: {}{}\n\nPieces: {pieces}\nPPS: {pps:.2}",
+                format_time(time),
+                if new_best { " (new best!)" } else { "" },
+            ),
+        }
+    }
+
+    /// Saves the splits so far. The personal best is loaded again instead.
+    fn save_state(&self) -> Value {
+        json!(self.splits)
+    }
+
+    fn restore_state(&mut self, state: Value) -> serde_json::Result<()> {
+        self.splits = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::Statistics;
+
+    fn progress(lines: u32, ticks: u64) -> Progress {
+        Progress {
+            score: 0,
+            level: 1,
+            lines,
+            ticks,
+            garbage_rows: 0,
+            garbage_cleared: 0,
+            statistics: Statistics::default(),
+        }
+    }
+
+    #[test]
+    fn splits() {
+        let mut sprint = Sprint {
+            lines: 25,
+            splits: Vec::new(),
+            personal_best: OnceCell::from(None),
+        };
+        assert!(!sprint.update(&progress(9, 600)));
+        assert!(sprint.splits.is_empty());
+
+        assert!(!sprint.update(&progress(21, 600)));
+        assert_eq!(sprint.splits, [10_000, 10_000]);
+
+        assert!(sprint.update(&progress(25, 1200)));
+        assert_eq!(sprint.splits, [10_000, 10_000, 20_000]);
+    }
+}
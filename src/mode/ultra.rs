@@ -0,0 +1,64 @@
+use super::{format_time, GameMode, Panel, Progress, Results};
+
+/// Timed score attack: score as many points as possible before time runs out.
+pub struct Ultra {
+    /// The time limit in seconds.
+    seconds: u32,
+}
+
+impl Ultra {
+    pub fn new(seconds: u32) -> Self {
+        Self { seconds }
+    }
+
+    /// Returns the time left in milliseconds.
+    fn remaining(&self, progress: &Progress) -> u64 {
+        (self.seconds as u64 * 1000).saturating_sub(progress.time())
+    }
+}
+
+impl GameMode for Ultra {
+    fn update(&mut self, progress: &Progress) -> bool {
+        self.remaining(progress) == 0
+    }
+
+    fn panels(&self, progress: &Progress) -> Vec<Panel> {
+        vec![
+            Panel::Value {
+                title: "SCORE",
+                value: progress.score.to_string(),
+            },
+            // Counts down in place of the level.
+            Panel::Value {
+                title: "TIME",
+                value: format_time(self.remaining(progress)),
+            },
+            Panel::Value {
+                title: "LINES",
+                value: progress.lines.to_string(),
+            },
+        ]
+    }
+
+    fn results(&mut self, progress: &Progress, completed: bool) -> Results {
+        if !completed {
+            return progress.game_over();
+        }
+        let statistics = &progress.statistics;
+        Results {
+            title: "Time's up!",
+            text: format!(
+                "Score: {}\n\nLines: {}\nSingles: {}\nDoubles: {}\nTriples: {}\n\
+                Tetrises: {}\nT-spins: {}\nPerfect clears: {}",
+                progress.score,
+                progress.lines,
+                statistics.singles,
+                statistics.doubles,
+                statistics.triples,
+                statistics.tetrises,
+                statistics.t_spins,
+                statistics.perfect_clears,
+            ),
+        }
+    }
+}
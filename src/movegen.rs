@@ -0,0 +1,158 @@
+//! Finds every placement a tetromino can reach, for bots, finesse checks and
+//! hints.
+
+use std::collections::{HashSet, VecDeque};
+
+use glam::{ivec2, IVec2};
+
+use crate::{board::Board, input::Input, tetromino::FallingTetromino};
+
+/// A final resting position of a tetromino and the shortest inputs that get it
+/// there.
+#[derive(Clone, Debug)]
+pub struct Placement {
+    pub tetromino: FallingTetromino,
+    /// Inputs starting from where the tetromino is now, ending with a hard drop.
+    /// Soft drops move down a single row.
+    pub inputs: Vec<Input>,
+}
+
+/// Inputs that move the tetromino without placing it, tried in this order.
+const MOVES: [Input; 5] = [
+    Input::MoveLeft,
+    Input::MoveRight,
+    Input::RotateClockwise,
+    Input::RotateCounterclockwise,
+    Input::SoftDrop,
+];
+
+/// Applies a move to `tetromino` following the rules of the board and its
+/// rotation system. Returns `None` if it's blocked.
+fn apply(board: &Board, tetromino: FallingTetromino, input: Input) -> Option<FallingTetromino> {
+    let moved = match input {
+        Input::MoveLeft => tetromino.moved(ivec2(-1, 0)),
+        Input::MoveRight => tetromino.moved(ivec2(1, 0)),
+        Input::SoftDrop => tetromino.moved(ivec2(0, 1)),
+        Input::RotateClockwise => {
+            return board.try_rotate(tetromino, 1).map(|(rotated, _)| rotated)
+        }
+        Input::RotateCounterclockwise => {
+            return board.try_rotate(tetromino, -1).map(|(rotated, _)| rotated)
+        }
+        Input::HardDrop | Input::Hold => return None,
+    };
+    board.can_fit(moved).then_some(moved)
+}
+
+/// Sorts the squares of a tetromino into a fixed order, so that rotations
+/// covering the same squares compare equal.
+pub fn sort_squares(mut squares: [IVec2; 4]) -> [IVec2; 4] {
+    squares.sort_by_key(|square| (square.x, square.y));
+    squares
+}
+
+/// Lists every placement `tetromino` can reach from where it is, including
+/// tucks under overhangs and spins into slots, with the shortest inputs for
+/// each. Placements covering the same squares are only listed once.
+///
+/// Searches all positions and rotations breadth-first, so the first time a
+/// placement is found after hard dropping it's through the fewest inputs.
+pub fn placements(board: &Board, tetromino: FallingTetromino) -> Vec<Placement> {
+    let mut placements = Vec::new();
+    let mut placed = HashSet::new();
+    let mut visited = HashSet::from([(tetromino.position(), tetromino.rotation())]);
+    let mut queue = VecDeque::from([(tetromino, Vec::new())]);
+
+    while let Some((current, inputs)) = queue.pop_front() {
+        let dropped = board.drop_position(current);
+        if placed.insert(sort_squares(dropped.squares())) {
+            let mut inputs = inputs.clone();
+            inputs.push(Input::HardDrop);
+            placements.push(Placement {
+                tetromino: dropped,
+                inputs,
+            });
+        }
+
+        for input in MOVES {
+            let Some(next) = apply(board, current, input) else {
+                continue;
+            };
+            if visited.insert((next.position(), next.rotation())) {
+                let mut inputs = inputs.clone();
+                inputs.push(input);
+                queue.push_back((next, inputs));
+            }
+        }
+    }
+    placements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{rotation::Srs, tetromino::Tetromino};
+
+    /// Spawns a tetromino at the top of a standard board.
+    fn spawn(tetromino: Tetromino) -> FallingTetromino {
+        FallingTetromino::spawn(tetromino, &Srs, Board::DEFAULT_WIDTH)
+    }
+
+    /// Returns the placement covering `squares`, if it was found.
+    fn find(placements: &[Placement], squares: [IVec2; 4]) -> Option<&Placement> {
+        placements
+            .iter()
+            .find(|placement| sort_squares(placement.tetromino.squares()) == sort_squares(squares))
+    }
+
+    #[test]
+    fn deduplicated_by_squares() {
+        let board = Board::default();
+        assert_eq!(placements(&board, spawn(Tetromino::O)).len(), 9);
+        assert_eq!(placements(&board, spawn(Tetromino::I)).len(), 17);
+        assert_eq!(placements(&board, spawn(Tetromino::T)).len(), 34);
+    }
+
+    #[test]
+    fn shortest_inputs() {
+        let board = Board::default();
+        let placements = placements(&board, spawn(Tetromino::O));
+        for placement in &placements {
+            let shifts = placement.inputs.len() - 1;
+            assert!(shifts <= 4, "{:?}", placement.inputs);
+            assert!(placement.inputs[..shifts]
+                .iter()
+                .all(|&input| input == placement.inputs[0]));
+        }
+    }
+
+    #[test]
+    fn tuck() {
+        let board = Board::from_ascii(&["###.......", ".........."]);
+        let placements = placements(&board, spawn(Tetromino::I));
+        let tucked = find(
+            &placements,
+            [ivec2(0, 19), ivec2(1, 19), ivec2(2, 19), ivec2(3, 19)],
+        )
+        .unwrap();
+        let inputs = &tucked.inputs;
+        assert!(inputs.contains(&Input::SoftDrop));
+        assert_eq!(inputs[inputs.len() - 2], Input::MoveLeft);
+    }
+
+    #[test]
+    fn t_spin_double() {
+        let board = Board::from_ascii(&["....#.....", "##...#####", "###.######"]);
+        let placements = placements(&board, spawn(Tetromino::T));
+        let spin = find(
+            &placements,
+            [ivec2(2, 18), ivec2(3, 18), ivec2(4, 18), ivec2(3, 19)],
+        )
+        .unwrap();
+        let inputs = &spin.inputs;
+        assert!(matches!(
+            inputs[inputs.len() - 2],
+            Input::RotateClockwise | Input::RotateCounterclockwise
+        ));
+    }
+}
@@ -0,0 +1,425 @@
+//! Randomizers decide the order in which tetrominoes are dealt.
+
+use std::{collections::VecDeque, iter};
+
+use rand::{seq::SliceRandom, Rng, RngCore};
+use serde::{de::Error, Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::tetromino::Tetromino;
+
+/// Generates the sequence of tetrominoes for a game.
+///
+/// Upcoming tetrominoes can be read ahead with [`Randomizer::peek`]. The
+/// built-in randomizers generate on demand, so wrap them in a [`PieceQueue`]
+/// to preview them.
+pub trait Randomizer {
+    /// Generates the next tetromino of the sequence.
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino;
+
+    /// Returns the upcoming tetrominoes that were already generated, in the
+    /// order [`Randomizer::generate`] will deal them.
+    fn peek(&self) -> Box<dyn Iterator<Item = Tetromino> + '_> {
+        Box::new(iter::empty())
+    }
+
+    /// Returns the state of the randomizer, for saving games.
+    fn save_state(&self) -> Value {
+        Value::Null
+    }
+
+    /// Restores a state returned by [`Randomizer::save_state`].
+    fn restore_state(&mut self, _state: Value) -> serde_json::Result<()> {
+        Ok(())
+    }
+}
+
+/// The built-in randomizers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RandomizerKind {
+    /// Every tetromino is equally likely, independent of previous ones.
+    Uniform,
+    /// Deals all 7 tetrominoes in random order, then repeats.
+    SevenBag,
+    /// Deals two of each tetromino in random order, then repeats.
+    FourteenBag,
+    /// TGM: rerolls up to 6 times if the piece is among the last 4.
+    TgmHistory,
+    /// NES: rerolls once if the piece is the same as the previous one.
+    Nes,
+}
+
+impl RandomizerKind {
+    pub const VARIANTS: [RandomizerKind; 5] = [
+        RandomizerKind::Uniform,
+        RandomizerKind::SevenBag,
+        RandomizerKind::FourteenBag,
+        RandomizerKind::TgmHistory,
+        RandomizerKind::Nes,
+    ];
+
+    /// Returns the name of this randomizer.
+    pub fn name(self) -> &'static str {
+        match self {
+            RandomizerKind::Uniform => "Uniform",
+            RandomizerKind::SevenBag => "7-bag",
+            RandomizerKind::FourteenBag => "14-bag",
+            RandomizerKind::TgmHistory => "TGM history",
+            RandomizerKind::Nes => "NES",
+        }
+    }
+
+    /// Creates a new randomizer of this kind.
+    pub fn build(self) -> Box<dyn Randomizer> {
+        match self {
+            RandomizerKind::Uniform => Box::new(Uniform),
+            RandomizerKind::SevenBag => Box::new(Bag::new(1)),
+            RandomizerKind::FourteenBag => Box::new(Bag::new(2)),
+            RandomizerKind::TgmHistory => Box::new(History::new(4, 6)),
+            RandomizerKind::Nes => Box::new(Nes::default()),
+        }
+    }
+}
+
+/// Draws every tetromino with equal probability.
+pub struct Uniform;
+
+impl Randomizer for Uniform {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino {
+        Tetromino::random(rng)
+    }
+}
+
+/// Deals tetrominoes from a shuffled bag holding `copies` of each tetromino. A new
+/// bag is filled once the previous one is empty.
+#[derive(Serialize)]
+pub struct Bag {
+    copies: usize,
+    bag: Vec<Tetromino>,
+}
+
+impl Bag {
+    /// Creates a new bag randomizer (1 copy for 7-bag, 2 for 14-bag).
+    pub fn new(copies: usize) -> Self {
+        Self {
+            copies,
+            bag: Vec::with_capacity(copies * Tetromino::VARIANTS.len()),
+        }
+    }
+}
+
+impl Randomizer for Bag {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino {
+        if self.bag.is_empty() {
+            for _ in 0..self.copies {
+                self.bag.extend(Tetromino::VARIANTS);
+            }
+            self.bag.shuffle(rng);
+        }
+        self.bag.pop().unwrap()
+    }
+
+    fn save_state(&self) -> Value {
+        json!(self)
+    }
+
+    /// Restores the tetrominoes left in the bag. The number of copies comes from
+    /// the randomizer kind, not the state.
+    fn restore_state(&mut self, state: Value) -> serde_json::Result<()> {
+        #[derive(Deserialize)]
+        struct State {
+            bag: Vec<Tetromino>,
+        }
+
+        let State { bag } = serde_json::from_value(state)?;
+        let too_many = Tetromino::VARIANTS
+            .iter()
+            .any(|tetromino| bag.iter().filter(|&t| t == tetromino).count() > self.copies);
+        if too_many {
+            return Err(Error::custom(format!(
+                "bag holds more than {} of a tetromino",
+                self.copies
+            )));
+        }
+        self.bag = bag;
+        Ok(())
+    }
+}
+
+/// Remembers the last few tetrominoes and rerolls a limited number of times if
+/// the drawn one is among them, as in TGM.
+#[derive(Serialize)]
+pub struct History {
+    history: VecDeque<Tetromino>,
+    rolls: usize,
+    first: bool,
+}
+
+impl History {
+    /// Creates a new history randomizer remembering `size` tetrominoes and drawing
+    /// at most `rolls` times per tetromino.
+    pub fn new(size: usize, rolls: usize) -> Self {
+        // TGM starts with a history full of Z.
+        Self {
+            history: VecDeque::from(vec![Tetromino::Z; size]),
+            rolls,
+            first: true,
+        }
+    }
+}
+
+impl Randomizer for History {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino {
+        let tetromino = if self.first {
+            // The first tetromino is never S, Z or O, so the game can't start with
+            // an overhang.
+            self.first = false;
+            [Tetromino::I, Tetromino::J, Tetromino::L, Tetromino::T][rng.gen_range(0..4)]
+        } else {
+            let mut tetromino = Tetromino::random(rng);
+            for _ in 1..self.rolls {
+                if !self.history.contains(&tetromino) {
+                    break;
+                }
+                tetromino = Tetromino::random(rng);
+            }
+            tetromino
+        };
+
+        self.history.pop_front();
+        self.history.push_back(tetromino);
+        tetromino
+    }
+
+    fn save_state(&self) -> Value {
+        json!(self)
+    }
+
+    /// Restores the remembered tetrominoes. The number of rolls comes from the
+    /// randomizer kind, not the state.
+    fn restore_state(&mut self, state: Value) -> serde_json::Result<()> {
+        #[derive(Deserialize)]
+        struct State {
+            history: VecDeque<Tetromino>,
+            first: bool,
+        }
+
+        let State { history, first } = serde_json::from_value(state)?;
+        if history.len() != self.history.len() {
+            return Err(Error::custom(format!(
+                "history holds {} tetrominoes instead of {}",
+                history.len(),
+                self.history.len()
+            )));
+        }
+        self.history = history;
+        self.first = first;
+        Ok(())
+    }
+}
+
+/// The NES randomizer: draws from 8 outcomes, and rerolls once (from the 7
+/// tetrominoes) if the result is the 8th outcome or repeats the previous
+/// tetromino.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Nes {
+    previous: Option<Tetromino>,
+}
+
+impl Randomizer for Nes {
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino {
+        let roll = rng.gen_range(0..=Tetromino::VARIANTS.len());
+        let tetromino = match Tetromino::VARIANTS.get(roll) {
+            Some(&tetromino) if Some(tetromino) != self.previous => tetromino,
+            _ => Tetromino::random(rng),
+        };
+        self.previous = Some(tetromino);
+        tetromino
+    }
+
+    fn save_state(&self) -> Value {
+        json!(self)
+    }
+
+    fn restore_state(&mut self, state: Value) -> serde_json::Result<()> {
+        *self = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+/// A queue of upcoming tetrominoes, generated by another [`Randomizer`].
+///
+/// The queue always holds at least `lookahead` tetrominoes, so they can be
+/// previewed with [`Randomizer::peek`] before they're dealt.
+///
+/// The randomizer isn't serialized with the queue. Deserialized queues use a
+/// [`Uniform`] randomizer until [`PieceQueue::set_randomizer`] is called.
+#[derive(Serialize, Deserialize)]
+pub struct PieceQueue {
+    #[serde(skip, default = "PieceQueue::unset_randomizer")]
+    randomizer: Box<dyn Randomizer>,
+    queue: VecDeque<Tetromino>,
+    lookahead: usize,
+}
+
+impl PieceQueue {
+    /// Creates a new queue and generates the first `lookahead` tetrominoes.
+    pub fn new(randomizer: Box<dyn Randomizer>, lookahead: usize, rng: &mut dyn RngCore) -> Self {
+        let mut queue = Self {
+            randomizer,
+            queue: VecDeque::with_capacity(lookahead + 1),
+            lookahead,
+        };
+        queue.fill(rng);
+        queue
+    }
+
+    /// Stands in for the randomizer of deserialized queues.
+    fn unset_randomizer() -> Box<dyn Randomizer> {
+        Box::new(Uniform)
+    }
+
+    /// Replaces the randomizer generating the tetrominoes.
+    pub fn set_randomizer(&mut self, randomizer: Box<dyn Randomizer>) {
+        self.randomizer = randomizer;
+    }
+
+    /// Generates tetrominoes until the lookahead is filled.
+    fn fill(&mut self, rng: &mut dyn RngCore) {
+        while self.queue.len() < self.lookahead {
+            self.queue.push_back(self.randomizer.generate(rng));
+        }
+    }
+}
+
+impl Randomizer for PieceQueue {
+    /// Removes and returns the next tetromino.
+    fn generate(&mut self, rng: &mut dyn RngCore) -> Tetromino {
+        let tetromino = match self.queue.pop_front() {
+            Some(tetromino) => tetromino,
+            None => self.randomizer.generate(rng),
+        };
+        self.fill(rng);
+        tetromino
+    }
+
+    fn peek(&self) -> Box<dyn Iterator<Item = Tetromino> + '_> {
+        Box::new(self.queue.iter().copied().take(self.lookahead))
+    }
+
+    /// Returns the state of the wrapped randomizer. The queued tetrominoes are
+    /// serialized with the queue itself.
+    fn save_state(&self) -> Value {
+        self.randomizer.save_state()
+    }
+
+    fn restore_state(&mut self, state: Value) -> serde_json::Result<()> {
+        self.randomizer.restore_state(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    fn sequence(kind: RandomizerKind, len: usize) -> Vec<Tetromino> {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut randomizer = kind.build();
+        (0..len).map(|_| randomizer.generate(&mut rng)).collect()
+    }
+
+    fn count(tetrominoes: &[Tetromino], tetromino: Tetromino) -> usize {
+        tetrominoes.iter().filter(|&&t| t == tetromino).count()
+    }
+
+    #[test]
+    fn seven_bag() {
+        for bag in sequence(RandomizerKind::SevenBag, 70).chunks(7) {
+            for tetromino in Tetromino::VARIANTS {
+                assert_eq!(count(bag, tetromino), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn fourteen_bag() {
+        for bag in sequence(RandomizerKind::FourteenBag, 140).chunks(14) {
+            for tetromino in Tetromino::VARIANTS {
+                assert_eq!(count(bag, tetromino), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn tgm_first_piece() {
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let first = RandomizerKind::TgmHistory.build().generate(&mut rng);
+            assert!(!matches!(first, Tetromino::S | Tetromino::Z | Tetromino::O));
+        }
+    }
+
+    #[test]
+    fn history_reduces_repeats() {
+        let repeats =
+            |tetrominoes: Vec<Tetromino>| tetrominoes.windows(2).filter(|w| w[0] == w[1]).count();
+        let uniform = repeats(sequence(RandomizerKind::Uniform, 7000));
+        let nes = repeats(sequence(RandomizerKind::Nes, 7000));
+        let tgm = repeats(sequence(RandomizerKind::TgmHistory, 7000));
+        assert!(nes < uniform / 2);
+        assert!(tgm < nes);
+    }
+
+    #[test]
+    fn queue_peek() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut queue = PieceQueue::new(RandomizerKind::SevenBag.build(), 3, &mut rng);
+        let peeked = queue.peek().collect::<Vec<_>>();
+        assert_eq!(peeked.len(), 3);
+        for tetromino in peeked {
+            assert_eq!(queue.generate(&mut rng), tetromino);
+        }
+        assert_eq!(queue.peek().count(), 3);
+        assert_eq!(RandomizerKind::SevenBag.build().peek().count(), 0);
+    }
+
+    #[test]
+    fn restored_state_continues_sequence() {
+        for kind in RandomizerKind::VARIANTS {
+            let mut rng = StdRng::seed_from_u64(1);
+            let mut randomizer = kind.build();
+            for _ in 0..10 {
+                randomizer.generate(&mut rng);
+            }
+
+            let mut restored = kind.build();
+            restored.restore_state(randomizer.save_state()).unwrap();
+            let mut restored_rng = rng.clone();
+            for _ in 0..20 {
+                assert_eq!(
+                    restored.generate(&mut restored_rng),
+                    randomizer.generate(&mut rng)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_tampered_state() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut bag = RandomizerKind::SevenBag.build();
+        // The number of copies comes from the kind, so the bag is refilled.
+        bag.restore_state(json!({ "copies": 0, "bag": [] }))
+            .unwrap();
+        bag.generate(&mut rng);
+        assert!(bag
+            .restore_state(json!({ "bag": [Tetromino::I, Tetromino::I] }))
+            .is_err());
+
+        let mut history = RandomizerKind::TgmHistory.build();
+        let state = json!({ "history": vec![Tetromino::Z; 5], "rolls": 6, "first": false });
+        assert!(history.restore_state(state).is_err());
+    }
+}
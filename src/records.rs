@@ -0,0 +1,58 @@
+//! Personal bests, stored as plain text files in the user's data directory.
+
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the directory game data is stored in, following platform
+/// conventions. Falls back to the working directory if none can be found.
+pub fn data_dir() -> PathBuf {
+    let base = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+    match base {
+        Some(base) => base.join("gridt"),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Returns the path of the personal best file for sprints of `lines` lines.
+fn sprint_path(lines: u32) -> PathBuf {
+    data_dir().join(format!("sprint_{lines}l.txt"))
+}
+
+/// Loads the split times (in milliseconds) of the fastest sprint of `lines`
+/// lines, if there is one.
+pub fn load_sprint(lines: u32) -> Option<Vec<u64>> {
+    let text = fs::read_to_string(sprint_path(lines)).ok()?;
+    parse_splits(&text)
+}
+
+/// Saves the split times (in milliseconds) of a sprint of `lines` lines as the
+/// new personal best.
+pub fn save_sprint(lines: u32, splits: &[u64]) -> io::Result<()> {
+    fs::create_dir_all(data_dir())?;
+    let text = splits
+        .iter()
+        .map(|split| format!("{split}\n"))
+        .collect::<String>();
+    fs::write(sprint_path(lines), text)
+}
+
+/// Parses split times, one per line. Returns `None` if any line is invalid.
+fn parse_splits(text: &str) -> Option<Vec<u64>> {
+    text.lines().map(|line| line.trim().parse().ok()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits() {
+        assert_eq!(parse_splits("1200\n2500\n"), Some(vec![1200, 2500]));
+        assert_eq!(parse_splits("1200\nfoo\n"), None);
+    }
+}
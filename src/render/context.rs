@@ -71,7 +71,7 @@ impl RenderContext {
         let square_renderer = SquareRenderer::new(
             &device,
             &config,
-            4 * (7 + Board::WIDTH * Board::HEIGHT) as u64,
+            4 * (7 + Board::DEFAULT_WIDTH * Board::DEFAULT_HEIGHT) as u64,
         );
         let quad_renderer = QuadRenderer::new(&device, &config, 16);
 
@@ -127,10 +127,10 @@ impl RenderContext {
         let proj_matrix = self.build_proj_mat();
 
         self.square_renderer
-            .render(&mut render_pass, &self.queue, proj_matrix)?;
+            .render(&mut render_pass, &self.device, &self.queue, proj_matrix)?;
 
         self.quad_renderer
-            .render(&mut render_pass, &self.queue, proj_matrix)?;
+            .render(&mut render_pass, &self.device, &self.queue, proj_matrix)?;
 
         drop(render_pass);
 
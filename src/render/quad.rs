@@ -43,6 +43,8 @@ pub struct QuadRenderer {
     proj_matrix_bind_group: BindGroup,
     pipeline: Pipeline,
     instance_buffer: Buffer,
+    /// Number of instances the instance buffer can hold.
+    max_instances: u64,
     instances: Vec<Quad>,
 }
 
@@ -76,22 +78,28 @@ impl QuadRenderer {
             &[Quad::desc()],
         );
 
-        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("quad renderer: instance buffer"),
-            size: max_instances * (std::mem::size_of::<Quad>() as u64),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let instance_buffer = Self::create_instance_buffer(device, max_instances);
 
         Self {
             proj_matrix_buffer,
             proj_matrix_bind_group,
             pipeline,
             instance_buffer,
+            max_instances,
             instances: Vec::with_capacity(max_instances as usize),
         }
     }
 
+    /// Creates an instance buffer holding up to `max_instances` quads.
+    fn create_instance_buffer(device: &Device, max_instances: u64) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("quad renderer: instance buffer"),
+            size: max_instances * (std::mem::size_of::<Quad>() as u64),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
     pub fn submit(&mut self, quad: Quad) {
         self.instances.push(quad);
     }
@@ -99,6 +107,7 @@ impl QuadRenderer {
     pub fn render<'a>(
         &'a mut self,
         render_pass: &mut RenderPass<'a>,
+        device: &Device,
         queue: &Queue,
         proj_matrix: Mat4,
     ) -> Result<(), SurfaceError> {
@@ -108,6 +117,13 @@ impl QuadRenderer {
             bytemuck::cast_slice(&[proj_matrix]),
         );
 
+        // Grow the instance buffer if more quads were submitted than fit, e.g.
+        // with several games on screen.
+        if self.instances.len() as u64 > self.max_instances {
+            self.max_instances = (self.instances.len() as u64).next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.max_instances);
+        }
+
         queue.write_buffer(
             &self.instance_buffer,
             0,
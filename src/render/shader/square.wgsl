@@ -6,11 +6,14 @@ struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
     @location(1) vert_pos: vec2<f32>,
+    @location(2) @interpolate(flat) style: u32,
 };
 
 struct InstanceInput {
     @location(1) position: vec2<f32>,
     @location(2) color: vec4<f32>,
+    @location(3) size: f32,
+    @location(4) style: u32,
 }
 
 // Vertex Shader
@@ -21,9 +24,10 @@ var<uniform> view_proj: mat4x4<f32>;
 @vertex
 fn vs_main(vert_in: VertexInput, inst_in: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
-    out.clip_position = view_proj * vec4<f32>(vert_in.position * 30.0 + inst_in.position, 0.0, 1.0);
+    out.clip_position = view_proj * vec4<f32>(vert_in.position * inst_in.size + inst_in.position, 0.0, 1.0);
     out.color = inst_in.color;
     out.vert_pos = vert_in.position;
+    out.style = inst_in.style;
     return out;
 }
 
@@ -31,6 +35,16 @@ fn vs_main(vert_in: VertexInput, inst_in: InstanceInput) -> VertexOutput {
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Outline only
+    if in.style == 1u {
+        if in.vert_pos.x > 0.1 && in.vert_pos.x < 0.9
+            && in.vert_pos.y > 0.1 && in.vert_pos.y < 0.9
+        {
+            discard;
+        }
+        return vec4<f32>(in.color.rgb * 0.7, in.color.a);
+    }
+
     var x: f32;
     // 0.99 instead of 1.0 is neccessary to align everything onto the pixel grid
     // perfectly due to float precision
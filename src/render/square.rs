@@ -34,15 +34,24 @@ impl Vertex {
 pub struct TetrominoSquare {
     pub position: Vec2,
     pub color: Vec4,
+    /// Width and height of the square in pixels.
+    pub size: f32,
+    /// How the square is drawn ([`Self::SOLID`] or [`Self::OUTLINE`]).
+    pub style: u32,
 }
 
 impl TetrominoSquare {
-    /// The size of a tetromino square in pixels.
+    /// The default size of a tetromino square in pixels.
     pub const SIZE: f32 = 30.0;
 
+    /// Draw the square filled and shaded.
+    pub const SOLID: u32 = 0;
+    /// Only draw the outline of the square.
+    pub const OUTLINE: u32 = 1;
+
     fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
-            wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x4];
+        const ATTRIBUTES: [wgpu::VertexAttribute; 4] =
+            wgpu::vertex_attr_array![1 => Float32x2, 2 => Float32x4, 3 => Float32, 4 => Uint32];
 
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
@@ -60,6 +69,8 @@ pub struct SquareRenderer {
     index_buffer: Buffer,
     index_count: u32,
     instance_buffer: Buffer,
+    /// Number of instances the instance buffer can hold.
+    max_instances: u64,
     instances: Vec<TetrominoSquare>,
 }
 
@@ -115,12 +126,7 @@ impl SquareRenderer {
             contents: bytemuck::cast_slice(INDICES),
             usage: wgpu::BufferUsages::INDEX,
         });
-        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("square renderer: instance buffer"),
-            size: max_instances * (std::mem::size_of::<TetrominoSquare>() as u64),
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        let instance_buffer = Self::create_instance_buffer(device, max_instances);
 
         Self {
             proj_matrix_buffer,
@@ -130,10 +136,21 @@ impl SquareRenderer {
             index_buffer,
             index_count: INDICES.len() as u32,
             instance_buffer,
+            max_instances,
             instances: Vec::with_capacity(max_instances as usize),
         }
     }
 
+    /// Creates an instance buffer holding up to `max_instances` squares.
+    fn create_instance_buffer(device: &Device, max_instances: u64) -> Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("square renderer: instance buffer"),
+            size: max_instances * (std::mem::size_of::<TetrominoSquare>() as u64),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
     pub fn submit_iter(&mut self, squares: impl Iterator<Item = TetrominoSquare>) {
         self.instances.extend(squares);
     }
@@ -141,6 +158,7 @@ impl SquareRenderer {
     pub fn render<'a>(
         &'a mut self,
         render_pass: &mut RenderPass<'a>,
+        device: &Device,
         queue: &Queue,
         proj_matrix: Mat4,
     ) -> Result<(), SurfaceError> {
@@ -150,6 +168,13 @@ impl SquareRenderer {
             bytemuck::cast_slice(&[proj_matrix]),
         );
 
+        // Grow the instance buffer if more squares were submitted than fit, e.g.
+        // for big boards.
+        if self.instances.len() as u64 > self.max_instances {
+            self.max_instances = (self.instances.len() as u64).next_power_of_two();
+            self.instance_buffer = Self::create_instance_buffer(device, self.max_instances);
+        }
+
         queue.write_buffer(
             &self.instance_buffer,
             0,
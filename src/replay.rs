@@ -0,0 +1,395 @@
+//! Replays: games recorded as their settings (including the seed) and the
+//! inputs pressed at each tick, and a scene that plays them back. Versus matches
+//! are recorded with the inputs of both players.
+
+use std::{
+    fs, io, iter,
+    path::{Path, PathBuf},
+};
+
+use glam::vec2;
+use serde::{Deserialize, Serialize};
+use wgpu::SurfaceError;
+use wgpu_glyph::{HorizontalAlign, Layout, Section, Text, VerticalAlign};
+use winit::event::{ElementState, KeyboardInput};
+
+use crate::{
+    archive::{self, Archive},
+    game::Game,
+    input::Input,
+    main_menu::MainMenu,
+    mode::format_time,
+    render::context::RenderContext,
+    scene::{Action, Scene},
+    settings::Settings,
+    versus,
+};
+
+/// An input pressed or released during a game.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputEvent {
+    /// Number of ticks played before the input happened.
+    pub tick: u64,
+    pub input: Input,
+    /// Whether the input was pressed or released.
+    pub pressed: bool,
+}
+
+/// A recorded game. Games are deterministic, so playing the same inputs at
+/// the same ticks with the same settings and seed plays out the same way.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Recording {
+    /// Version of the file format, so old replays aren't misread.
+    pub version: u32,
+    /// The settings the game was played with, always with a seed.
+    pub settings: Settings,
+    /// Number of ticks the game lasted.
+    pub ticks: u64,
+    /// All inputs, in the order they happened. In versus, the inputs of the
+    /// left player.
+    pub events: Vec<InputEvent>,
+    /// In versus, all inputs of the right player, including those of a bot.
+    /// Both players' games use `settings`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opponent_events: Option<Vec<InputEvent>>,
+}
+
+impl Recording {
+    /// The current version of the file format.
+    pub const VERSION: u32 = 1;
+
+    /// Combines the recordings of both players of a versus match.
+    pub fn versus(left: &Game, right: &Game) -> Self {
+        let (left, right) = (left.recording(), right.recording());
+        Self {
+            ticks: left.ticks.max(right.ticks),
+            opponent_events: Some(right.events),
+            ..left
+        }
+    }
+
+    /// Saves the recording in `replays`. Returns the path of the file.
+    pub fn save(&self, replays: &Archive) -> io::Result<PathBuf> {
+        replays.add(&self.to_json())
+    }
+
+    /// Loads a recording from a file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("recordings can always be serialized")
+    }
+
+    /// Parses a recording, rejecting other versions of the file format and
+    /// invalid settings.
+    fn from_json(json: &str) -> io::Result<Self> {
+        archive::check_version("replay", Self::VERSION, json)?;
+        let recording: Self = serde_json::from_str(json)?;
+        recording.settings.validate()?;
+        Ok(recording)
+    }
+}
+
+/// Plays back a recording, with pause, speed control, frame-stepping and
+/// seeking.
+pub struct Replay {
+    recording: Recording,
+    /// The recorded game, or the left and right player's games in versus.
+    games: Vec<Game>,
+    /// Index of the next event to feed into each game.
+    next_events: Vec<usize>,
+    paused: bool,
+    /// Index into [`Replay::SPEEDS`].
+    speed: usize,
+    /// Ticks to play but not played yet, for speeds below 1x.
+    pending_ticks: f32,
+}
+
+impl Replay {
+    /// Playback speeds to choose from.
+    const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+    /// Index of 1x in [`Replay::SPEEDS`].
+    const NORMAL_SPEED: usize = 2;
+    /// How far seeking jumps, in ticks.
+    const SEEK_TICKS: u64 = 5 * 60;
+
+    pub fn new(recording: Recording) -> Self {
+        let mut replay = Self {
+            recording,
+            games: Vec::new(),
+            next_events: Vec::new(),
+            paused: false,
+            speed: Self::NORMAL_SPEED,
+            pending_ticks: 0.0,
+        };
+        replay.restart();
+        replay
+    }
+
+    /// Starts playing the recording from the beginning.
+    fn restart(&mut self) {
+        let players = if self.recording.opponent_events.is_some() {
+            2
+        } else {
+            1
+        };
+        self.games = (0..players)
+            .map(|_| Game::new(self.recording.settings))
+            .collect();
+        self.next_events = vec![0; players];
+    }
+
+    /// Returns the number of ticks played back so far.
+    fn tick_count(&self) -> u64 {
+        self.games
+            .iter()
+            .map(|game| game.progress().ticks)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Checks whether the recorded game is over. Versus matches are over once
+    /// a player topped out.
+    fn is_over(&self) -> bool {
+        self.games.iter().any(Game::is_over)
+    }
+
+    /// Feeds the inputs of the current tick into the games and advances them by
+    /// a tick. In versus, the garbage the players sent is exchanged afterwards.
+    fn step(&mut self) {
+        let events = iter::once(&self.recording.events).chain(&self.recording.opponent_events);
+        for ((game, next_event), events) in
+            self.games.iter_mut().zip(&mut self.next_events).zip(events)
+        {
+            let tick = game.progress().ticks;
+            while let Some(event) = events.get(*next_event).filter(|event| event.tick <= tick) {
+                if event.pressed {
+                    game.press(event.input);
+                } else {
+                    game.release(event.input);
+                }
+                *next_event += 1;
+            }
+            game.update();
+        }
+        if let [left, right] = self.games.as_mut_slice() {
+            versus::exchange_garbage(left, right);
+        }
+    }
+
+    /// Jumps to `tick`. Going back plays the game again from the start.
+    fn seek(&mut self, tick: u64) {
+        if tick < self.tick_count() {
+            self.restart();
+        }
+        while self.tick_count() < tick.min(self.recording.ticks) && !self.is_over() {
+            self.step();
+        }
+    }
+}
+
+impl Scene for Replay {
+    fn keyboard_input(&mut self, input: KeyboardInput) -> Action {
+        if input.state != ElementState::Pressed {
+            return Action::Continue;
+        }
+        match input.scancode {
+            // Back to main menu [Esc]
+            1 => {
+                let settings = Settings {
+                    seed: None,
+                    ..self.recording.settings
+                };
+                return Action::SwitchScene(Box::new(MainMenu::new(settings)));
+            }
+            // Pause [Space]
+            57 => self.paused = !self.paused,
+            // Step a single tick while paused [.]
+            52 if self.paused => self.step(),
+            // Faster [Up]
+            57416 => self.speed = (self.speed + 1).min(Self::SPEEDS.len() - 1),
+            // Slower [Down]
+            57424 => self.speed = self.speed.saturating_sub(1),
+            // Seek back [Left]
+            57419 => self.seek(self.tick_count().saturating_sub(Self::SEEK_TICKS)),
+            // Seek forward [Right]
+            57421 => self.seek(self.tick_count() + Self::SEEK_TICKS),
+            _ => (),
+        }
+        Action::Continue
+    }
+
+    fn tick(&mut self) -> Action {
+        if self.paused {
+            return Action::Continue;
+        }
+        self.pending_ticks += Self::SPEEDS[self.speed];
+        while self.pending_ticks >= 1.0 {
+            self.pending_ticks -= 1.0;
+            if !self.is_over() {
+                self.step();
+            }
+        }
+        Action::Continue
+    }
+
+    /// Renders a single game centered with the controls on its left, or both
+    /// games of a versus match side by side with the controls below.
+    fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError> {
+        let ticks_to_ms = |ticks: u64| ticks * 1000 / 60;
+        let status = format!(
+            "REPLAY{}\n{}x\n{} / {}",
+            if self.paused { " (PAUSED)" } else { "" },
+            Self::SPEEDS[self.speed],
+            format_time(ticks_to_ms(self.tick_count())),
+            format_time(ticks_to_ms(self.recording.ticks)),
+        );
+        let controls = [
+            "Space: pause",
+            ".: step",
+            "Up/Down: speed",
+            "Left/Right: seek",
+        ];
+
+        let width = ctx.config.width as f32;
+        if let [game] = self.games.as_slice() {
+            let x = ((width - Game::SIZE.x) / 2.0).max(0.0);
+            game.render_at(ctx, vec2(x, 0.0));
+            let text = format!("{status}\n\n{}", controls.join("\n"));
+            ctx.glyph_brush.queue(Section {
+                screen_position: (20.0, 20.0),
+                text: vec![Text::new(&text)
+                    .with_color([1.0, 1.0, 1.0, 1.0])
+                    .with_scale(24.0)],
+                bounds: (x, f32::INFINITY),
+                layout: Layout::default_wrap(),
+            });
+        } else {
+            let half_width = width / 2.0;
+            let x = ((half_width - Game::SIZE.x) / 2.0).max(0.0);
+            for (i, game) in self.games.iter().enumerate() {
+                game.render_at(ctx, vec2(i as f32 * half_width + x, 0.0));
+            }
+            // There's only room for a single line below the games.
+            let text = format!("{}   {}", status.replace('\n', "   "), controls.join("   "));
+            ctx.glyph_brush.queue(Section {
+                screen_position: (half_width, ctx.config.height as f32 - 5.0),
+                text: vec![Text::new(&text)
+                    .with_color([1.0, 1.0, 1.0, 1.0])
+                    .with_scale(18.0)],
+                bounds: (width, f32::INFINITY),
+                layout: Layout::default_single_line()
+                    .h_align(HorizontalAlign::Center)
+                    .v_align(VerticalAlign::Bottom),
+            });
+        }
+
+        ctx.render_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::{Bot, Weights};
+
+    #[test]
+    fn plays_back_the_same_game() {
+        let mut game = Game::new(Settings {
+            seed: Some(11),
+            ..Settings::default()
+        });
+        let mut bot = Bot::new(Weights::default(), 3);
+        for _ in 0..1000 {
+            bot.update(&mut game);
+            game.update();
+        }
+
+        let recording = Recording::from_json(&game.recording().to_json()).unwrap();
+        let mut replay = Replay::new(recording);
+        replay.seek(u64::MAX);
+        assert_eq!(replay.tick_count(), 1000);
+        assert_eq!(replay.games[0].progress().score, game.progress().score);
+        assert_eq!(
+            replay.games[0].progress().statistics.pieces,
+            game.progress().statistics.pieces
+        );
+
+        // Seeking back plays the game again from the start.
+        replay.seek(500);
+        assert_eq!(replay.tick_count(), 500);
+    }
+
+    #[test]
+    fn plays_back_versus_against_the_bot() {
+        let settings = Settings {
+            seed: Some(4),
+            ..Settings::default()
+        };
+        let mut players = [Game::new(settings), Game::new(settings)];
+        let mut bots = [
+            Bot::new(Weights::default(), 3),
+            Bot::new(Weights::default(), 1),
+        ];
+        for _ in 0..2000 {
+            if players.iter().any(Game::is_over) {
+                break;
+            }
+            for (bot, player) in bots.iter_mut().zip(&mut players) {
+                bot.update(player);
+                player.update();
+            }
+            let [left, right] = &mut players;
+            versus::exchange_garbage(left, right);
+        }
+        let [left, right] = &players;
+        assert!(left.progress().statistics.attack > 0 && right.progress().statistics.attack > 0);
+
+        let recording = Recording::from_json(&Recording::versus(left, right).to_json()).unwrap();
+        let mut replay = Replay::new(recording);
+        replay.seek(u64::MAX);
+        for (replayed, played) in replay.games.iter().zip(&players) {
+            assert_eq!(replayed.progress().ticks, played.progress().ticks);
+            assert_eq!(replayed.progress().score, played.progress().score);
+            assert_eq!(
+                replayed.progress().garbage_rows,
+                played.progress().garbage_rows
+            );
+        }
+    }
+
+    #[test]
+    fn records_only_changes() {
+        let mut game = Game::new(Settings::default());
+        // Key repeats and releases of keys that aren't held.
+        game.release(Input::MoveLeft);
+        game.press(Input::MoveLeft);
+        game.press(Input::MoveLeft);
+        game.press(Input::MoveLeft);
+        game.release(Input::MoveLeft);
+        game.release(Input::MoveLeft);
+        let pressed = game
+            .recording()
+            .events
+            .iter()
+            .map(|event| event.pressed)
+            .collect::<Vec<_>>();
+        assert_eq!(pressed, [true, false]);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut recording = Game::new(Settings::default()).recording();
+        recording.version = Recording::VERSION + 1;
+        assert!(Recording::from_json(&recording.to_json()).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_settings() {
+        let mut recording = Game::new(Settings::default()).recording();
+        recording.settings.preview_count = 0;
+        assert!(Recording::from_json(&recording.to_json()).is_err());
+    }
+}
@@ -0,0 +1,456 @@
+//! Rotation systems.
+//!
+//! A rotation system decides the shape of every tetromino in each of its four
+//! orientations, where new tetrominoes spawn and which alternative positions
+//! ("kicks") are tried when a rotation is obstructed.
+
+use std::fmt::Debug;
+
+use glam::{ivec2, IVec2};
+
+use crate::{
+    board::Board,
+    tetromino::{FallingTetromino, Tetromino},
+};
+
+/// All built-in rotation systems.
+pub const ROTATION_SYSTEMS: [&dyn RotationSystem; 3] = [&Srs, &Ars, &Nes];
+
+/// (De)serializes a rotation system as its name, for use with
+/// `#[serde(with = "by_name")]`.
+pub mod by_name {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::{RotationSystem, ROTATION_SYSTEMS};
+
+    pub fn serialize<S: Serializer>(
+        rotation_system: &&'static dyn RotationSystem,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(rotation_system.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<&'static dyn RotationSystem, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        ROTATION_SYSTEMS
+            .into_iter()
+            .find(|rotation_system| rotation_system.name() == name)
+            .ok_or_else(|| D::Error::custom(format!("unknown rotation system {name:?}")))
+    }
+}
+
+/// Shapes, spawn positions and kick logic for tetrominoes.
+///
+/// Tetromino shapes are given as squares on a 4x4 grid. Rotation state 0 is the
+/// spawn orientation, 1 is rotated clockwise, 2 is upside down and 3 is rotated
+/// counterclockwise. All coordinates use board coordinates, so positive `y`
+/// points down.
+pub trait RotationSystem: Debug + Sync {
+    /// Returns the name of this rotation system.
+    fn name(&self) -> &'static str;
+
+    /// Returns the positions of squares which represent `tetromino` on a 4x4 grid
+    /// in the given rotation state.
+    fn squares(&self, tetromino: Tetromino, rotation: u8) -> [IVec2; 4];
+
+    /// Returns the position of the 4x4 grid of a newly spawned tetromino on a
+    /// board `board_width` squares wide.
+    fn spawn_position(&self, tetromino: Tetromino, board_width: usize) -> IVec2;
+
+    /// Returns the offsets to try, in order, when rotating `tetromino` from
+    /// rotation state `from` to `to`. The first offset should be `(0, 0)`.
+    fn kicks(&self, tetromino: Tetromino, from: u8, to: u8) -> &'static [IVec2];
+
+    /// Rotates a falling tetromino on `board` by `by` (see
+    /// [`FallingTetromino::rotated`]), trying each kick in order.
+    ///
+    /// Returns the rotated tetromino and the index of the kick that succeeded, or
+    /// `None` if the tetromino can't be rotated.
+    fn rotate(
+        &self,
+        board: &Board,
+        tetromino: FallingTetromino,
+        by: i8,
+    ) -> Option<(FallingTetromino, usize)> {
+        let rotated = tetromino.rotated(by);
+        self.kicks(
+            tetromino.tetromino,
+            tetromino.rotation(),
+            rotated.rotation(),
+        )
+        .iter()
+        .map(|&kick| rotated.moved(kick))
+        .enumerate()
+        .find(|&(_, kicked)| board.can_fit(kicked))
+        .map(|(kick, kicked)| (kicked, kick))
+    }
+}
+
+/// Rotation systems are told apart by name.
+impl PartialEq for dyn RotationSystem {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+const NO_KICKS: &[IVec2] = &[ivec2(0, 0)];
+
+/// The Super Rotation System used by guideline games.
+///
+/// ```text
+/// I      J      L      O      S      T      Z
+/// ....   #..    ..#    .##.   .##    .#.    ##.
+/// ####   ###    ###    .##.   ##.    ###    .##
+/// ....   ...    ...    ....   ...    ...    ...
+/// ....
+/// ```
+#[derive(Debug)]
+pub struct Srs;
+
+impl Srs {
+    // Kick tables are usually published with positive y pointing up. These are
+    // flipped to board coordinates.
+    const JLSTZ_0_R: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(-1, 0),
+        ivec2(-1, -1),
+        ivec2(0, 2),
+        ivec2(-1, 2),
+    ];
+    const JLSTZ_R_0: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(1, 0),
+        ivec2(1, 1),
+        ivec2(0, -2),
+        ivec2(1, -2),
+    ];
+    const JLSTZ_2_L: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(1, 0),
+        ivec2(1, -1),
+        ivec2(0, 2),
+        ivec2(1, 2),
+    ];
+    const JLSTZ_L_2: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(-1, 0),
+        ivec2(-1, 1),
+        ivec2(0, -2),
+        ivec2(-1, -2),
+    ];
+    const I_0_R: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(-2, 0),
+        ivec2(1, 0),
+        ivec2(-2, 1),
+        ivec2(1, -2),
+    ];
+    const I_R_0: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(2, 0),
+        ivec2(-1, 0),
+        ivec2(2, -1),
+        ivec2(-1, 2),
+    ];
+    const I_R_2: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(-1, 0),
+        ivec2(2, 0),
+        ivec2(-1, -2),
+        ivec2(2, 1),
+    ];
+    const I_2_R: &'static [IVec2] = &[
+        ivec2(0, 0),
+        ivec2(1, 0),
+        ivec2(-2, 0),
+        ivec2(1, 2),
+        ivec2(-2, -1),
+    ];
+}
+
+impl RotationSystem for Srs {
+    fn name(&self) -> &'static str {
+        "SRS"
+    }
+
+    fn squares(&self, tetromino: Tetromino, rotation: u8) -> [IVec2; 4] {
+        match (tetromino, rotation % 4) {
+            (Tetromino::I, 0) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
+            (Tetromino::I, 1) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(2, 3)],
+            (Tetromino::I, 2) => [ivec2(0, 2), ivec2(1, 2), ivec2(2, 2), ivec2(3, 2)],
+            (Tetromino::I, 3) => [ivec2(1, 0), ivec2(1, 1), ivec2(1, 2), ivec2(1, 3)],
+            (Tetromino::O, _) => [ivec2(1, 0), ivec2(2, 0), ivec2(1, 1), ivec2(2, 1)],
+            (Tetromino::J, 0) => [ivec2(0, 0), ivec2(0, 1), ivec2(1, 1), ivec2(2, 1)],
+            (Tetromino::J, 1) => [ivec2(1, 0), ivec2(2, 0), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::J, 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::J, 3) => [ivec2(1, 0), ivec2(1, 1), ivec2(0, 2), ivec2(1, 2)],
+            (Tetromino::L, 0) => [ivec2(2, 0), ivec2(0, 1), ivec2(1, 1), ivec2(2, 1)],
+            (Tetromino::L, 1) => [ivec2(1, 0), ivec2(1, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::L, 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(0, 2)],
+            (Tetromino::L, 3) => [ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::S, 0) => [ivec2(1, 0), ivec2(2, 0), ivec2(0, 1), ivec2(1, 1)],
+            (Tetromino::S, 1) => [ivec2(1, 0), ivec2(1, 1), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::S, 2) => [ivec2(1, 1), ivec2(2, 1), ivec2(0, 2), ivec2(1, 2)],
+            (Tetromino::S, 3) => [ivec2(0, 0), ivec2(0, 1), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::T, 0) => [ivec2(1, 0), ivec2(0, 1), ivec2(1, 1), ivec2(2, 1)],
+            (Tetromino::T, 1) => [ivec2(1, 0), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            (Tetromino::T, 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            (Tetromino::T, 3) => [ivec2(1, 0), ivec2(0, 1), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::Z, 0) => [ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(2, 1)],
+            (Tetromino::Z, 1) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            (Tetromino::Z, 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::Z, 3) => [ivec2(1, 0), ivec2(0, 1), ivec2(1, 1), ivec2(0, 2)],
+            _ => unreachable!(),
+        }
+    }
+
+    fn spawn_position(&self, _tetromino: Tetromino, board_width: usize) -> IVec2 {
+        // 3-wide tetrominoes spawn left of center.
+        ivec2((board_width as i32 - 3) / 2, 0)
+    }
+
+    fn kicks(&self, tetromino: Tetromino, from: u8, to: u8) -> &'static [IVec2] {
+        match (tetromino, from % 4, to % 4) {
+            (Tetromino::O, _, _) => NO_KICKS,
+            (Tetromino::I, 0, 1) | (Tetromino::I, 3, 2) => Self::I_0_R,
+            (Tetromino::I, 1, 0) | (Tetromino::I, 2, 3) => Self::I_R_0,
+            (Tetromino::I, 1, 2) | (Tetromino::I, 0, 3) => Self::I_R_2,
+            (Tetromino::I, 2, 1) | (Tetromino::I, 3, 0) => Self::I_2_R,
+            (_, 0, 1) | (_, 2, 1) => Self::JLSTZ_0_R,
+            (_, 1, 0) | (_, 1, 2) => Self::JLSTZ_R_0,
+            (_, 2, 3) | (_, 0, 3) => Self::JLSTZ_2_L,
+            (_, 3, 2) | (_, 3, 0) => Self::JLSTZ_L_2,
+            // 180 degree rotations don't kick.
+            _ => NO_KICKS,
+        }
+    }
+}
+
+/// The Arika Rotation System used by the TGM series.
+///
+/// Tetrominoes rest on the bottom of their 3x3 box and kick one square to the
+/// right, then to the left. `I` never kicks and `J`, `L` and `T` don't kick when
+/// the center column blocks the rotation.
+///
+/// ```text
+/// I      J      L      O      S      T      Z
+/// ....   ...    ...    ....   ...    ...    ...
+/// ####   ###    ###    .##.   .##    ###    ##.
+/// ....   ..#    #..    .##.   ##.    .#.    .##
+/// ....
+/// ```
+#[derive(Debug)]
+pub struct Ars;
+
+impl Ars {
+    const KICKS: &'static [IVec2] = &[ivec2(0, 0), ivec2(1, 0), ivec2(-1, 0)];
+}
+
+impl RotationSystem for Ars {
+    fn name(&self) -> &'static str {
+        "ARS"
+    }
+
+    fn squares(&self, tetromino: Tetromino, rotation: u8) -> [IVec2; 4] {
+        match (tetromino, rotation % 4) {
+            (Tetromino::I, 0 | 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
+            (Tetromino::I, 1 | 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(2, 3)],
+            (Tetromino::O, _) => [ivec2(1, 1), ivec2(2, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::J, 0) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::J, 1) => [ivec2(1, 0), ivec2(1, 1), ivec2(0, 2), ivec2(1, 2)],
+            (Tetromino::J, 2) => [ivec2(0, 1), ivec2(0, 2), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::J, 3) => [ivec2(1, 0), ivec2(2, 0), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::L, 0) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(0, 2)],
+            (Tetromino::L, 1) => [ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::L, 2) => [ivec2(2, 1), ivec2(0, 2), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::L, 3) => [ivec2(1, 0), ivec2(1, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::S, 0 | 2) => [ivec2(1, 1), ivec2(2, 1), ivec2(0, 2), ivec2(1, 2)],
+            (Tetromino::S, 1 | 3) => [ivec2(0, 0), ivec2(0, 1), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::T, 0) => [ivec2(0, 1), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            (Tetromino::T, 1) => [ivec2(1, 0), ivec2(0, 1), ivec2(1, 1), ivec2(1, 2)],
+            (Tetromino::T, 2) => [ivec2(1, 1), ivec2(0, 2), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::T, 3) => [ivec2(1, 0), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            (Tetromino::Z, 0 | 2) => [ivec2(0, 1), ivec2(1, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::Z, 1 | 3) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(1, 2)],
+            _ => unreachable!(),
+        }
+    }
+
+    fn spawn_position(&self, _tetromino: Tetromino, board_width: usize) -> IVec2 {
+        ivec2((board_width as i32 - 3) / 2, -1)
+    }
+
+    fn kicks(&self, tetromino: Tetromino, _from: u8, _to: u8) -> &'static [IVec2] {
+        match tetromino {
+            Tetromino::I | Tetromino::O => NO_KICKS,
+            _ => Self::KICKS,
+        }
+    }
+
+    fn rotate(
+        &self,
+        board: &Board,
+        tetromino: FallingTetromino,
+        by: i8,
+    ) -> Option<(FallingTetromino, usize)> {
+        let rotated = tetromino.rotated(by);
+        if board.can_fit(rotated) {
+            return Some((rotated, 0));
+        }
+
+        // Center column rule: if the first obstructed square of the rotated
+        // tetromino's 3x3 box (in reading order) is in the center column, J, L and
+        // T don't kick.
+        if let Tetromino::J | Tetromino::L | Tetromino::T = tetromino.tetromino {
+            let mut squares = self.squares(rotated.tetromino, rotated.rotation());
+            squares.sort_by_key(|square| (square.y, square.x));
+            let first_blocked = squares
+                .into_iter()
+                .find(|&square| board.is_blocked(square + rotated.position()));
+            if let Some(IVec2 { x: 1, .. }) = first_blocked {
+                return None;
+            }
+        }
+
+        self.kicks(
+            tetromino.tetromino,
+            tetromino.rotation(),
+            rotated.rotation(),
+        )
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(kick, &offset)| (rotated.moved(offset), kick))
+        .find(|&(kicked, _)| board.can_fit(kicked))
+    }
+}
+
+/// The rotation system of the NES version. Tetrominoes rotate in place and never
+/// kick.
+///
+/// ```text
+/// I      J      L      O      S      T      Z
+/// ....   ....   ....   ....   ....   ....   ....
+/// ....   .###   .###   .##.   ..##   .###   .##.
+/// ####   ...#   .#..   .##.   .##.   ..#.   ..##
+/// ....
+/// ```
+#[derive(Debug)]
+pub struct Nes;
+
+impl RotationSystem for Nes {
+    fn name(&self) -> &'static str {
+        "NES"
+    }
+
+    fn squares(&self, tetromino: Tetromino, rotation: u8) -> [IVec2; 4] {
+        match (tetromino, rotation % 4) {
+            (Tetromino::I, 0 | 2) => [ivec2(0, 2), ivec2(1, 2), ivec2(2, 2), ivec2(3, 2)],
+            (Tetromino::I, 1 | 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(2, 3)],
+            (Tetromino::O, _) => [ivec2(1, 1), ivec2(2, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::J, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(3, 2)],
+            (Tetromino::J, 1) => [ivec2(2, 0), ivec2(2, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::J, 2) => [ivec2(1, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
+            (Tetromino::J, 3) => [ivec2(2, 0), ivec2(3, 0), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::L, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(1, 2)],
+            (Tetromino::L, 1) => [ivec2(1, 0), ivec2(2, 0), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::L, 2) => [ivec2(3, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
+            (Tetromino::L, 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(3, 2)],
+            (Tetromino::S, 0 | 2) => [ivec2(2, 1), ivec2(3, 1), ivec2(1, 2), ivec2(2, 2)],
+            (Tetromino::S, 1 | 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(3, 1), ivec2(3, 2)],
+            (Tetromino::Z, 0 | 2) => [ivec2(1, 1), ivec2(2, 1), ivec2(2, 2), ivec2(3, 2)],
+            (Tetromino::Z, 1 | 3) => [ivec2(3, 0), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
+            (Tetromino::T, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
+            (Tetromino::T, 1) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(2, 2)],
+            (Tetromino::T, 2) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
+            (Tetromino::T, 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
+            _ => unreachable!(),
+        }
+    }
+
+    fn spawn_position(&self, _tetromino: Tetromino, board_width: usize) -> IVec2 {
+        ivec2((board_width as i32 - 4) / 2, -1)
+    }
+
+    fn kicks(&self, _tetromino: Tetromino, _from: u8, _to: u8) -> &'static [IVec2] {
+        NO_KICKS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_have_four_distinct_squares() {
+        for rs in ROTATION_SYSTEMS {
+            for tetromino in Tetromino::VARIANTS {
+                for rotation in 0..4 {
+                    let mut squares = rs.squares(tetromino, rotation).to_vec();
+                    squares.sort_by_key(|s| (s.x, s.y));
+                    squares.dedup();
+                    assert_eq!(squares.len(), 4, "{} {tetromino:?} {rotation}", rs.name());
+                    assert!(squares
+                        .iter()
+                        .all(|s| s.cmpge(IVec2::ZERO).all() && s.cmplt(IVec2::splat(4)).all()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn spawn_centered() {
+        for rs in ROTATION_SYSTEMS {
+            for tetromino in Tetromino::VARIANTS {
+                let spawned = FallingTetromino::spawn(tetromino, rs, Board::DEFAULT_WIDTH);
+                let xs = spawned.squares().map(|s| s.x);
+                let (min, max) = (*xs.iter().min().unwrap(), *xs.iter().max().unwrap());
+                assert!(min >= 3 && max <= 6, "{} {tetromino:?}", rs.name());
+            }
+        }
+    }
+
+    #[test]
+    fn nes_never_kicks() {
+        let board = Board::default();
+        // T pointing right against the left wall.
+        let tetromino = FallingTetromino::spawn(Tetromino::T, &Nes, Board::DEFAULT_WIDTH)
+            .rotated(-1)
+            .moved(ivec2(-5, 5));
+        assert!(board.can_fit(tetromino));
+        assert!(board.try_rotate(tetromino, 1).is_none());
+    }
+
+    #[test]
+    fn ars_kicks_right() {
+        let board = Board::default();
+        // T pointing right against the left wall.
+        let tetromino = FallingTetromino::spawn(Tetromino::T, &Ars, Board::DEFAULT_WIDTH)
+            .rotated(-1)
+            .moved(ivec2(-4, 5));
+        assert!(board.can_fit(tetromino));
+        let (rotated, kick) = board.try_rotate(tetromino, 1).unwrap();
+        assert_eq!(kick, 1);
+        assert_eq!(rotated.rotation(), 0);
+    }
+
+    #[test]
+    fn ars_center_column_rule() {
+        let tetromino =
+            FallingTetromino::spawn(Tetromino::L, &Ars, Board::DEFAULT_WIDTH).moved(ivec2(0, 18));
+
+        // Blocked at the side: kicks to the right.
+        let board = Board::from_ascii(&["...#......", "..........", ".........."]);
+        assert!(board.can_fit(tetromino));
+        let (_, kick) = board.try_rotate(tetromino, 1).unwrap();
+        assert_eq!(kick, 1);
+
+        // Blocked in the center column: no kick, even though a kick to the left
+        // would fit.
+        let board = Board::from_ascii(&["....#.....", "..........", ".........."]);
+        assert!(board.can_fit(tetromino));
+        assert!(board.try_rotate(tetromino, 1).is_none());
+    }
+}
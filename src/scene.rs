@@ -13,6 +13,10 @@ pub trait Scene {
 
     /// Renders scene.
     fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError>;
+
+    /// Called before the game exits, like when the window is closed. Scenes that
+    /// can be continued later save their state here; the others are dropped.
+    fn exit(&mut self) {}
 }
 
 /// Action to be performed after a scene handler method returns.
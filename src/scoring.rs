@@ -0,0 +1,372 @@
+//! Guideline scoring: line clears are worth more at higher levels, and
+//! consecutive clears (combos) and back-to-back difficult clears earn bonuses.
+
+use std::fmt::{self, Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// Points per cell moved down by soft dropping.
+const SOFT_DROP_POINTS: u32 = 1;
+/// Points per cell moved down by hard dropping.
+const HARD_DROP_POINTS: u32 = 2;
+/// Points per combo step, multiplied by level.
+const COMBO_POINTS: u32 = 50;
+/// Garbage rows sent in versus by each combo step. Longer combos send as many
+/// as the last entry.
+const COMBO_ATTACK: [u32; 11] = [0, 1, 1, 2, 2, 3, 3, 4, 4, 4, 5];
+/// Garbage rows sent in versus for a back-to-back clear, on top of the clear.
+const BACK_TO_BACK_ATTACK: u32 = 1;
+/// Garbage rows sent in versus for a perfect clear, on top of the clear.
+const PERFECT_CLEAR_ATTACK: u32 = 10;
+
+/// A line clear, named by the number of rows cleared at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineClear {
+    Single,
+    Double,
+    Triple,
+    Tetris,
+}
+
+impl LineClear {
+    /// Returns the line clear for a number of rows, or `None` if no rows were
+    /// cleared.
+    pub fn from_rows(rows: u8) -> Option<Self> {
+        match rows {
+            0 => None,
+            1 => Some(LineClear::Single),
+            2 => Some(LineClear::Double),
+            3 => Some(LineClear::Triple),
+            _ => Some(LineClear::Tetris),
+        }
+    }
+
+    /// Returns the name shown when this clear happens.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineClear::Single => "SINGLE",
+            LineClear::Double => "DOUBLE",
+            LineClear::Triple => "TRIPLE",
+            LineClear::Tetris => "TETRIS",
+        }
+    }
+}
+
+/// A T-spin: the last action before placing a T tetromino was a rotation into a
+/// spot with at least 3 of its 4 diagonal corners blocked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TSpin {
+    /// Only one of the corners the T points towards is blocked.
+    Mini,
+    /// Both corners the T points towards are blocked.
+    Full,
+}
+
+impl TSpin {
+    /// Returns the name shown when this T-spin happens.
+    pub fn name(self) -> &'static str {
+        match self {
+            TSpin::Mini => "MINI T-SPIN",
+            TSpin::Full => "T-SPIN",
+        }
+    }
+}
+
+/// Returns the points for a placement at level 1.
+fn base_points(clear: Option<LineClear>, t_spin: Option<TSpin>) -> u32 {
+    match (t_spin, clear) {
+        (None, None) => 0,
+        (None, Some(LineClear::Single)) => 100,
+        (None, Some(LineClear::Double)) => 300,
+        (None, Some(LineClear::Triple)) => 500,
+        (None, Some(LineClear::Tetris)) => 800,
+        (Some(TSpin::Mini), None) => 100,
+        (Some(TSpin::Mini), Some(LineClear::Single)) => 200,
+        (Some(TSpin::Mini), Some(_)) => 400,
+        (Some(TSpin::Full), None) => 400,
+        (Some(TSpin::Full), Some(LineClear::Single)) => 800,
+        (Some(TSpin::Full), Some(LineClear::Double)) => 1200,
+        (Some(TSpin::Full), Some(_)) => 1600,
+    }
+}
+
+/// Returns the garbage rows a line clear sends in versus, without bonuses.
+fn base_attack(clear: LineClear, t_spin: Option<TSpin>) -> u32 {
+    match (t_spin, clear) {
+        (None, LineClear::Single) => 0,
+        (None, LineClear::Double) => 1,
+        (None, LineClear::Triple) => 2,
+        (None, LineClear::Tetris) => 4,
+        (Some(TSpin::Mini), LineClear::Single) => 0,
+        (Some(TSpin::Mini), _) => 1,
+        (Some(TSpin::Full), LineClear::Single) => 2,
+        (Some(TSpin::Full), LineClear::Double) => 4,
+        (Some(TSpin::Full), _) => 6,
+    }
+}
+
+/// Returns the bonus for a line clear that leaves the board empty, at level 1.
+fn perfect_clear_points(clear: LineClear, back_to_back: bool) -> u32 {
+    match clear {
+        LineClear::Single => 800,
+        LineClear::Double => 1200,
+        LineClear::Triple => 1800,
+        LineClear::Tetris if back_to_back => 3200,
+        LineClear::Tetris => 2000,
+    }
+}
+
+/// The points awarded for a line clear or T-spin, itemized.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    /// The rows cleared, if any. T-spins score even without clearing rows.
+    pub clear: Option<LineClear>,
+    pub t_spin: Option<TSpin>,
+    /// Points for the clear itself, multiplied by level.
+    pub clear_points: u32,
+    /// Whether the clear continued a back-to-back chain.
+    pub back_to_back: bool,
+    /// Extra points for the back-to-back chain.
+    pub back_to_back_points: u32,
+    /// How many clears in a row came before this one.
+    pub combo: u32,
+    /// Extra points for the combo.
+    pub combo_points: u32,
+    /// Whether the clear left the board empty.
+    pub perfect_clear: bool,
+    /// Extra points for the perfect clear.
+    pub perfect_clear_points: u32,
+}
+
+impl ScoreBreakdown {
+    /// Returns the sum of all points.
+    pub fn total(&self) -> u32 {
+        self.clear_points + self.back_to_back_points + self.combo_points + self.perfect_clear_points
+    }
+
+    /// Returns the number of garbage rows this sends to the opponent in versus,
+    /// following the guideline attack table. T-spins without clears send nothing.
+    pub fn attack(&self) -> u32 {
+        let Some(clear) = self.clear else {
+            return 0;
+        };
+        let combo = COMBO_ATTACK[(self.combo as usize).min(COMBO_ATTACK.len() - 1)];
+        let mut attack = base_attack(clear, self.t_spin) + combo;
+        if self.back_to_back {
+            attack += BACK_TO_BACK_ATTACK;
+        }
+        if self.perfect_clear {
+            attack += PERFECT_CLEAR_ATTACK;
+        }
+        attack
+    }
+}
+
+impl Display for ScoreBreakdown {
+    /// Formats the breakdown like "T-SPIN DOUBLE B2B COMBO 2 +1900".
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let names = [
+            self.t_spin.map(TSpin::name),
+            self.clear.map(LineClear::name),
+        ];
+        let name = names.into_iter().flatten().collect::<Vec<_>>().join(" ");
+        write!(f, "{name}")?;
+        if self.back_to_back {
+            write!(f, " B2B")?;
+        }
+        if self.perfect_clear {
+            write!(f, " PERFECT CLEAR")?;
+        }
+        if self.combo > 0 {
+            write!(f, " COMBO {}", self.combo)?;
+        }
+        write!(f, " +{}", self.total())
+    }
+}
+
+/// Keeps track of combos and back-to-back chains across placements.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Scoring {
+    /// Number of consecutive placements that cleared rows, if the last one did.
+    combo: Option<u32>,
+    /// Whether the last line clear was difficult.
+    back_to_back: bool,
+}
+
+impl Scoring {
+    /// Returns how many clears in a row came before the next one, if it clears
+    /// rows.
+    pub fn combo(&self) -> u32 {
+        self.combo.map_or(0, |combo| combo + 1)
+    }
+
+    /// Returns whether a difficult clear next would continue a back-to-back
+    /// chain.
+    pub fn back_to_back(&self) -> bool {
+        self.back_to_back
+    }
+
+    /// Scores a placement that cleared `rows` rows at `level`, possibly with a
+    /// T-spin or leaving the board empty (`perfect_clear`). Returns `None` if the
+    /// placement earned nothing.
+    ///
+    /// Placements that don't clear rows end the combo but keep back-to-back
+    /// chains going. Tetrises and T-spins that clear rows are difficult and
+    /// continue the chain, other clears break it.
+    pub fn clear(
+        &mut self,
+        rows: u8,
+        t_spin: Option<TSpin>,
+        perfect_clear: bool,
+        level: u32,
+    ) -> Option<ScoreBreakdown> {
+        let clear = LineClear::from_rows(rows);
+        if clear.is_none() {
+            self.combo = None;
+            t_spin?;
+        }
+
+        let clear_points = base_points(clear, t_spin) * level;
+        let mut back_to_back = false;
+        let mut combo = 0;
+        if clear.is_some() {
+            let difficult = clear == Some(LineClear::Tetris) || t_spin.is_some();
+            back_to_back = difficult && self.back_to_back;
+            self.back_to_back = difficult;
+            combo = self.combo.map_or(0, |combo| combo + 1);
+            self.combo = Some(combo);
+        }
+
+        Some(ScoreBreakdown {
+            clear,
+            t_spin,
+            clear_points,
+            back_to_back,
+            back_to_back_points: if back_to_back { clear_points / 2 } else { 0 },
+            combo,
+            combo_points: COMBO_POINTS * combo * level,
+            perfect_clear: perfect_clear && clear.is_some(),
+            perfect_clear_points: match clear {
+                Some(clear) if perfect_clear => perfect_clear_points(clear, back_to_back) * level,
+                _ => 0,
+            },
+        })
+    }
+
+    /// Returns the points for soft dropping `cells` cells.
+    pub fn soft_drop(cells: u32) -> u32 {
+        SOFT_DROP_POINTS * cells
+    }
+
+    /// Returns the points for hard dropping `cells` cells.
+    pub fn hard_drop(cells: u32) -> u32 {
+        HARD_DROP_POINTS * cells
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn level_multiplier() {
+        let mut scoring = Scoring::default();
+        let breakdown = scoring.clear(2, None, false, 3).unwrap();
+        assert_eq!(breakdown.clear, Some(LineClear::Double));
+        assert_eq!(breakdown.total(), 900);
+    }
+
+    #[test]
+    fn back_to_back_tetris() {
+        let mut scoring = Scoring::default();
+        assert_eq!(scoring.clear(4, None, false, 1).unwrap().total(), 800);
+        assert_eq!(scoring.clear(0, None, false, 1), None);
+        let breakdown = scoring.clear(4, None, false, 1).unwrap();
+        assert!(breakdown.back_to_back);
+        assert_eq!(breakdown.total(), 1200);
+        assert_eq!(breakdown.to_string(), "TETRIS B2B +1200");
+
+        // Easier clears break the chain.
+        scoring.clear(1, None, false, 1);
+        scoring.clear(0, None, false, 1);
+        assert!(!scoring.clear(4, None, false, 1).unwrap().back_to_back);
+    }
+
+    #[test]
+    fn combo() {
+        let mut scoring = Scoring::default();
+        assert_eq!(scoring.clear(1, None, false, 2).unwrap().combo, 0);
+        let breakdown = scoring.clear(1, None, false, 2).unwrap();
+        assert_eq!(breakdown.combo, 1);
+        assert_eq!(breakdown.combo_points, 100);
+        assert_eq!(scoring.clear(1, None, false, 2).unwrap().combo, 2);
+
+        scoring.clear(0, None, false, 2);
+        assert_eq!(scoring.clear(1, None, false, 2).unwrap().combo, 0);
+    }
+
+    #[test]
+    fn more_than_four_rows() {
+        let mut scoring = Scoring::default();
+        let breakdown = scoring.clear(5, None, false, 1).unwrap();
+        assert_eq!(breakdown.clear, Some(LineClear::Tetris));
+    }
+
+    #[test]
+    fn t_spins() {
+        let mut scoring = Scoring::default();
+        let breakdown = scoring.clear(0, Some(TSpin::Full), false, 1).unwrap();
+        assert_eq!(breakdown.to_string(), "T-SPIN +400");
+        assert_eq!(
+            scoring
+                .clear(1, Some(TSpin::Mini), false, 1)
+                .unwrap()
+                .to_string(),
+            "MINI T-SPIN SINGLE +200"
+        );
+        // T-spins without clears keep the back-to-back chain going.
+        scoring.clear(0, Some(TSpin::Mini), false, 1);
+        let breakdown = scoring.clear(2, Some(TSpin::Full), false, 1).unwrap();
+        assert_eq!(breakdown.to_string(), "T-SPIN DOUBLE B2B +1800");
+    }
+
+    #[test]
+    fn perfect_clear() {
+        let mut scoring = Scoring::default();
+        let breakdown = scoring.clear(2, None, true, 2).unwrap();
+        assert_eq!(breakdown.perfect_clear_points, 2400);
+        assert_eq!(breakdown.to_string(), "DOUBLE PERFECT CLEAR +3000");
+
+        scoring.clear(4, None, false, 1);
+        let breakdown = scoring.clear(4, None, true, 1).unwrap();
+        assert_eq!(breakdown.perfect_clear_points, 3200);
+    }
+
+    #[test]
+    fn attack() {
+        let mut scoring = Scoring::default();
+        assert_eq!(scoring.clear(1, None, false, 1).unwrap().attack(), 0);
+        // Combo 1.
+        assert_eq!(scoring.clear(4, None, false, 1).unwrap().attack(), 5);
+        scoring.clear(0, None, false, 1);
+        // Back-to-back.
+        assert_eq!(scoring.clear(4, None, false, 1).unwrap().attack(), 5);
+        scoring.clear(0, None, false, 1);
+        assert_eq!(
+            scoring
+                .clear(0, Some(TSpin::Full), false, 1)
+                .unwrap()
+                .attack(),
+            0
+        );
+        assert_eq!(
+            scoring
+                .clear(2, Some(TSpin::Full), false, 1)
+                .unwrap()
+                .attack(),
+            5
+        );
+        scoring.clear(0, None, false, 1);
+        assert_eq!(scoring.clear(2, None, true, 1).unwrap().attack(), 11);
+    }
+}
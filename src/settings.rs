@@ -0,0 +1,167 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    mode::ModeKind,
+    randomizer::RandomizerKind,
+    rotation::{RotationSystem, Srs},
+};
+
+/// Options chosen before a game starts.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Settings {
+    /// The goal of the game.
+    pub mode: ModeKind,
+    /// Decides tetromino shapes, spawn positions and wall kicks.
+    #[serde(with = "crate::rotation::by_name")]
+    pub rotation_system: &'static dyn RotationSystem,
+    /// Decides the order in which tetrominoes are dealt.
+    pub randomizer: RandomizerKind,
+    /// How many upcoming tetrominoes are shown (1 to [`Settings::MAX_PREVIEW_COUNT`]).
+    pub preview_count: usize,
+    /// Whether to show where the falling tetromino will land.
+    pub ghost_piece: bool,
+    /// Number of ticks a tetromino can rest on the stack before it's placed (1
+    /// to [`Settings::MAX_DELAY`]).
+    pub lock_delay: u32,
+    /// Which actions reset the lock delay.
+    pub lock_reset: LockReset,
+    /// Delayed auto shift: ticks a movement key has to be held before the
+    /// tetromino starts moving repeatedly. At most [`Settings::MAX_DELAY`].
+    pub das: u32,
+    /// Auto repeat rate: ticks between moves once auto shift started. 0 moves the
+    /// tetromino all the way instantly. At most [`Settings::MAX_DELAY`].
+    pub arr: u32,
+    /// How many times faster tetrominoes fall while soft dropping (1 to
+    /// [`Settings::MAX_SOFT_DROP_FACTOR`]).
+    pub soft_drop_factor: u32,
+    /// Number of columns of the board, at least [`Settings::MIN_BOARD_SIZE`].
+    pub board_width: usize,
+    /// Number of visible rows of the board, at least [`Settings::MIN_BOARD_SIZE`].
+    pub board_height: usize,
+    /// The level the game starts at (1 to [`Settings::MAX_START_LEVEL`]).
+    pub start_level: u32,
+    /// Lines to clear to advance to the next level. Can't be 0.
+    pub lines_per_level: u32,
+    /// Seed for the game's random number generator. A random seed is picked if
+    /// this is `None`.
+    pub seed: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mode: ModeKind::Marathon,
+            rotation_system: &Srs,
+            randomizer: RandomizerKind::SevenBag,
+            preview_count: 5,
+            ghost_piece: true,
+            lock_delay: 30,
+            lock_reset: LockReset::Move { limit: 15 },
+            das: 10,
+            arr: 2,
+            soft_drop_factor: 20,
+            board_width: 10,
+            board_height: 20,
+            start_level: 1,
+            lines_per_level: 10,
+            seed: None,
+        }
+    }
+}
+
+impl Settings {
+    /// The most upcoming tetrominoes that can be shown.
+    pub const MAX_PREVIEW_COUNT: usize = 6;
+    /// The fewest columns and visible rows a board can have, so every tetromino
+    /// fits on it.
+    pub const MIN_BOARD_SIZE: usize = 4;
+    /// The highest level a game can start at.
+    pub const MAX_START_LEVEL: u32 = 20;
+    /// The longest lock delay, DAS and ARR, in ticks.
+    pub const MAX_DELAY: u32 = 10 * 60;
+    /// The highest soft drop factor. Anything faster is an instant drop anyway.
+    pub const MAX_SOFT_DROP_FACTOR: u32 = 1000;
+
+    /// Checks that games can be played with these settings. Settings read from
+    /// save and replay files are checked with this, since the files may have
+    /// been edited.
+    pub fn validate(&self) -> io::Result<()> {
+        let problem = if !(1..=Self::MAX_PREVIEW_COUNT).contains(&self.preview_count) {
+            format!("can't preview {} tetrominoes", self.preview_count)
+        } else if self.board_width < Self::MIN_BOARD_SIZE
+            || self.board_height < Self::MIN_BOARD_SIZE
+        {
+            format!(
+                "board {}x{} is too small",
+                self.board_width, self.board_height
+            )
+        } else if !(1..=Self::MAX_START_LEVEL).contains(&self.start_level) {
+            format!("can't start at level {}", self.start_level)
+        } else if self.lines_per_level == 0 {
+            "lines per level can't be 0".to_string()
+        } else if !(1..=Self::MAX_DELAY).contains(&self.lock_delay) {
+            format!("can't use a lock delay of {} ticks", self.lock_delay)
+        } else if self.das > Self::MAX_DELAY || self.arr > Self::MAX_DELAY {
+            format!("DAS {} or ARR {} is too slow", self.das, self.arr)
+        } else if !(1..=Self::MAX_SOFT_DROP_FACTOR).contains(&self.soft_drop_factor) {
+            format!("can't soft drop {}x as fast", self.soft_drop_factor)
+        } else if let Some(problem) = self.mode_problem() {
+            problem
+        } else {
+            return Ok(());
+        };
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid settings: {problem}"),
+        ))
+    }
+
+    /// Returns what's wrong with the parameters of the mode, if anything.
+    fn mode_problem(&self) -> Option<String> {
+        match self.mode {
+            ModeKind::Sprint { lines: 0 } => Some("can't sprint 0 lines".to_string()),
+            ModeKind::Dig { rows } if rows as usize > self.board_height => Some(format!(
+                "{rows} garbage rows don't fit on a board {} rows high",
+                self.board_height
+            )),
+            ModeKind::DigSurvival { seconds: 0 } => {
+                Some("garbage can't rise every 0 seconds".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Decides which actions reset the lock delay of a resting tetromino.
+///
+/// With every policy, the lock delay is reset when a tetromino moves down to a row
+/// lower than it has been before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LockReset {
+    /// Moving or rotating resets the lock delay, up to `limit` times per row. Once
+    /// the limit is used up the tetromino is placed as soon as it touches down.
+    Move { limit: u32 },
+    /// Only moving down resets the lock delay.
+    Step,
+    /// Moving or rotating always resets the lock delay.
+    Infinite,
+}
+
+impl LockReset {
+    pub const VARIANTS: [LockReset; 3] = [
+        LockReset::Move { limit: 15 },
+        LockReset::Step,
+        LockReset::Infinite,
+    ];
+
+    /// Returns the name of this policy.
+    pub fn name(self) -> &'static str {
+        match self {
+            LockReset::Move { .. } => "move reset",
+            LockReset::Step => "step reset",
+            LockReset::Infinite => "infinite",
+        }
+    }
+}
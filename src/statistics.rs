@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::scoring::{LineClear, ScoreBreakdown};
+
+/// Counts of what happened during a game, shown once it's over.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Statistics {
+    /// Number of tetrominoes placed.
+    pub pieces: u32,
+    pub singles: u32,
+    pub doubles: u32,
+    pub triples: u32,
+    pub tetrises: u32,
+    /// Number of T-spins, including minis and those without clears.
+    pub t_spins: u32,
+    pub perfect_clears: u32,
+    /// The longest combo reached.
+    pub max_combo: u32,
+    /// Garbage rows sent in versus, including those that cancelled received
+    /// garbage.
+    pub attack: u32,
+}
+
+/// Totals of all games finished since the program started.
+static SESSION: Mutex<Statistics> = Mutex::new(Statistics::NONE);
+
+impl Statistics {
+    /// Statistics of a game where nothing happened yet.
+    const NONE: Statistics = Statistics {
+        pieces: 0,
+        singles: 0,
+        doubles: 0,
+        triples: 0,
+        tetrises: 0,
+        t_spins: 0,
+        perfect_clears: 0,
+        max_combo: 0,
+        attack: 0,
+    };
+
+    /// Adds the counts of `other` to these, keeping the longer combo.
+    pub fn add(&mut self, other: &Statistics) {
+        self.pieces += other.pieces;
+        self.singles += other.singles;
+        self.doubles += other.doubles;
+        self.triples += other.triples;
+        self.tetrises += other.tetrises;
+        self.t_spins += other.t_spins;
+        self.perfect_clears += other.perfect_clears;
+        self.max_combo = self.max_combo.max(other.max_combo);
+        self.attack += other.attack;
+    }
+
+    /// Adds the statistics of a finished game to the session totals, and returns
+    /// the new totals.
+    pub fn add_to_session(&self) -> Statistics {
+        let mut session = SESSION.lock().unwrap_or_else(|err| err.into_inner());
+        session.add(self);
+        *session
+    }
+
+    /// Records a placed tetromino and what it scored, if anything.
+    pub fn record(&mut self, breakdown: Option<&ScoreBreakdown>) {
+        self.pieces += 1;
+        let Some(breakdown) = breakdown else {
+            return;
+        };
+
+        match breakdown.clear {
+            Some(LineClear::Single) => self.singles += 1,
+            Some(LineClear::Double) => self.doubles += 1,
+            Some(LineClear::Triple) => self.triples += 1,
+            Some(LineClear::Tetris) => self.tetrises += 1,
+            None => (),
+        }
+        if breakdown.t_spin.is_some() {
+            self.t_spins += 1;
+        }
+        if breakdown.perfect_clear {
+            self.perfect_clears += 1;
+        }
+        self.max_combo = self.max_combo.max(breakdown.combo);
+        self.attack += breakdown.attack();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add() {
+        let mut total = Statistics {
+            perfect_clears: 1,
+            max_combo: 4,
+            ..Statistics::default()
+        };
+        total.add(&Statistics {
+            pieces: 10,
+            perfect_clears: 2,
+            max_combo: 3,
+            ..Statistics::default()
+        });
+        assert_eq!(total.pieces, 10);
+        assert_eq!(total.perfect_clears, 3);
+        assert_eq!(total.max_combo, 4);
+    }
+}
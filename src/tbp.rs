@@ -0,0 +1,812 @@
+//! Frontend for the Tetris Bot Protocol (TBP), which lets external bots like
+//! Cold Clear play games. Messages are JSON objects, one per line, exchanged
+//! over the bot's stdin and stdout.
+//!
+//! Board coordinates in TBP have row 0 at the bottom of the board, growing up.
+
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    iter,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use glam::{ivec2, IVec2};
+use serde::{Deserialize, Serialize};
+use wgpu::SurfaceError;
+use winit::event::{ElementState, KeyboardInput};
+
+use crate::{
+    archive::Archive,
+    board::{Board, Cell},
+    game::Game,
+    input::Input,
+    movegen::{self, sort_squares, Placement},
+    render::context::RenderContext,
+    rotation::{RotationSystem, Srs},
+    scene::{Action, Scene},
+    settings::Settings,
+    tetromino::Tetromino,
+};
+
+/// A message from the frontend to the bot.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum FrontendMessage {
+    /// Tells the bot about the rules, after it sent its info.
+    Rules,
+    /// Starts a game from the given state.
+    Start(Start),
+    /// Asks for moves for the current tetromino.
+    Suggest,
+    /// Tells the bot which move was played.
+    Play {
+        #[serde(rename = "move")]
+        played: Move,
+    },
+    /// Tells the bot about a tetromino that was added to the queue.
+    NewPiece {
+        piece: Tetromino,
+    },
+    Stop,
+    Quit,
+}
+
+/// The state a game is started from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Start {
+    hold: Option<Tetromino>,
+    /// The current tetromino, followed by the next queue.
+    queue: Vec<Tetromino>,
+    combo: u32,
+    back_to_back: bool,
+    /// Rows from the bottom up, each square being the letter of the tetromino
+    /// that occupies it, `G` for garbage or `None` if it's empty.
+    board: Vec<Vec<Option<char>>>,
+}
+
+/// A message from the bot to the frontend.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BotMessage {
+    /// Sent by the bot when it starts.
+    Info {
+        name: String,
+        version: String,
+        author: String,
+        features: Vec<String>,
+    },
+    /// The bot accepts the rules.
+    Ready,
+    /// The bot can't play with the rules.
+    Error { reason: String },
+    /// Moves for the current tetromino, best first.
+    Suggestion { moves: Vec<Move> },
+}
+
+/// A placement of a tetromino.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub location: PieceLocation,
+    pub spin: Spin,
+}
+
+/// Where a tetromino is and how it's rotated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PieceLocation {
+    #[serde(rename = "type")]
+    pub tetromino: Tetromino,
+    pub orientation: Orientation,
+    /// Column of the tetromino's center of rotation.
+    pub x: i32,
+    /// Row of the tetromino's center of rotation, counting from the bottom.
+    pub y: i32,
+}
+
+impl PieceLocation {
+    /// Returns the squares of the tetromino in TBP coordinates.
+    fn tbp_squares(&self) -> [IVec2; 4] {
+        let north = match self.tetromino {
+            Tetromino::I => [ivec2(-1, 0), ivec2(0, 0), ivec2(1, 0), ivec2(2, 0)],
+            Tetromino::O => [ivec2(0, 0), ivec2(1, 0), ivec2(0, 1), ivec2(1, 1)],
+            Tetromino::T => [ivec2(-1, 0), ivec2(0, 0), ivec2(1, 0), ivec2(0, 1)],
+            Tetromino::L => [ivec2(-1, 0), ivec2(0, 0), ivec2(1, 0), ivec2(1, 1)],
+            Tetromino::J => [ivec2(-1, 0), ivec2(0, 0), ivec2(1, 0), ivec2(-1, 1)],
+            Tetromino::S => [ivec2(-1, 0), ivec2(0, 0), ivec2(0, 1), ivec2(1, 1)],
+            Tetromino::Z => [ivec2(-1, 1), ivec2(0, 1), ivec2(0, 0), ivec2(1, 0)],
+        };
+        north.map(|offset| {
+            let rotated = (0..self.orientation as u8).fold(offset, |offset, _| {
+                // Clockwise, with y pointing up.
+                ivec2(offset.y, -offset.x)
+            });
+            ivec2(self.x, self.y) + rotated
+        })
+    }
+
+    /// Returns the squares of the tetromino on `board`.
+    pub fn squares(&self, board: &Board) -> [IVec2; 4] {
+        self.tbp_squares()
+            .map(|square| ivec2(square.x, board.height() as i32 - 1 - square.y))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    North,
+    East,
+    South,
+    West,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Spin {
+    None,
+    Mini,
+    Full,
+}
+
+/// Returns the letter a square is described with in TBP.
+fn cell_letter(cell: Cell) -> char {
+    match cell {
+        Cell::Tetromino(tetromino) => match tetromino {
+            Tetromino::I => 'I',
+            Tetromino::J => 'J',
+            Tetromino::L => 'L',
+            Tetromino::O => 'O',
+            Tetromino::T => 'T',
+            Tetromino::Z => 'Z',
+            Tetromino::S => 'S',
+        },
+        Cell::Garbage => 'G',
+    }
+}
+
+/// Describes the squares of `board` row by row, from the bottom up. TBP boards
+/// are always 10x40, so this only fits boards of the default size (see
+/// [`TbpGame::new`]).
+fn board_rows(board: &Board) -> Vec<Vec<Option<char>>> {
+    let height = board.height() as i32;
+    (0..2 * height)
+        .map(|y| {
+            (0..board.width() as i32)
+                .map(|x| board.cell(ivec2(x, height - 1 - y)).map(cell_letter))
+                .collect()
+        })
+        .collect()
+}
+
+/// Describes the state of `game` for starting a bot.
+fn start_message(game: &Game) -> Start {
+    Start {
+        hold: game.held_tetromino(),
+        queue: iter::once(game.falling_tetromino().tetromino)
+            .chain(game.queue())
+            .collect(),
+        combo: game.scoring().combo(),
+        back_to_back: game.scoring().back_to_back(),
+        board: board_rows(game.board()),
+    }
+}
+
+/// Returns the placement of `played` in `game`, with inputs holding first if
+/// it's for the hold tetromino. Returns `None` if the move can't be reached.
+fn move_placement(game: &Game, played: &Move) -> Option<Placement> {
+    let target = sort_squares(played.location.squares(game.board()));
+    let falling = game.falling_tetromino();
+    let (tetromino, mut inputs) = if played.location.tetromino == falling.tetromino {
+        (falling, Vec::new())
+    } else {
+        let held = game
+            .hold_candidate()
+            .filter(|&held| held == played.location.tetromino)?;
+        (game.spawned(held), vec![Input::Hold])
+    };
+    let placement = movegen::placements(game.board(), tetromino)
+        .into_iter()
+        .find(|placement| sort_squares(placement.tetromino.squares()) == target)?;
+    inputs.extend(placement.inputs);
+    Some(Placement {
+        tetromino: placement.tetromino,
+        inputs,
+    })
+}
+
+/// An external bot playing a game through TBP.
+///
+/// The bot's messages are read on a separate thread, so a slow bot doesn't
+/// hold up the game while it's thinking.
+pub struct TbpBot<W: Write> {
+    /// Messages from the bot, or the error that stopped reading them.
+    messages: Receiver<io::Result<BotMessage>>,
+    writer: W,
+    /// The bot's name, from its info message.
+    name: String,
+    /// The state the bot knows the game to be in, or `None` if it wasn't
+    /// started yet.
+    expected: Option<Start>,
+    /// The state the bot was asked for a move in and when it has to answer by,
+    /// while it's thinking.
+    asked: Option<(Start, Instant)>,
+    /// How long the bot gets to answer.
+    timeout: Duration,
+    /// The bot's process, if it was spawned by [`TbpBot::spawn`].
+    child: Option<Child>,
+}
+
+impl TbpBot<ChildStdin> {
+    /// Starts a bot process and talks to it over its stdin and stdout. The
+    /// process is stopped when the bot is dropped.
+    pub fn spawn(program: &str, args: &[&str]) -> io::Result<Self> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+        match Self::new(BufReader::new(stdout), stdin) {
+            Ok(mut bot) => {
+                bot.child = Some(child);
+                Ok(bot)
+            }
+            Err(err) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                Err(err)
+            }
+        }
+    }
+}
+
+impl<W: Write> TbpBot<W> {
+    /// How long the bot gets to answer a message before it's given up on.
+    const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+    /// How long a spawned bot gets to exit after being told to quit before it's
+    /// killed.
+    const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Waits for the bot's info, sends the rules and waits until the bot
+    /// accepts them.
+    pub fn new(reader: impl BufRead + Send + 'static, writer: W) -> io::Result<Self> {
+        let (sender, messages) = mpsc::channel();
+        thread::spawn(move || read_messages(reader, sender));
+        let mut bot = Self {
+            messages,
+            writer,
+            name: String::new(),
+            expected: None,
+            asked: None,
+            timeout: Self::ANSWER_TIMEOUT,
+            child: None,
+        };
+        match bot.receive()? {
+            BotMessage::Info { name, .. } => bot.name = name,
+            message => return Err(unexpected(&message)),
+        }
+        bot.send(&FrontendMessage::Rules)?;
+        match bot.receive()? {
+            BotMessage::Ready => Ok(bot),
+            BotMessage::Error { reason } => Err(io::Error::other(reason)),
+            message => Err(unexpected(&message)),
+        }
+    }
+
+    /// Returns the name the bot introduced itself with.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Checks whether the bot was asked for a move and didn't answer yet.
+    pub fn is_thinking(&self) -> bool {
+        self.asked.is_some()
+    }
+
+    fn send(&mut self, message: &FrontendMessage) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+
+    /// Waits for the next message, for up to the bot's timeout.
+    fn receive(&mut self) -> io::Result<BotMessage> {
+        match self.messages.recv_timeout(self.timeout) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => Err(timed_out()),
+            Err(RecvTimeoutError::Disconnected) => Err(closed()),
+        }
+    }
+
+    /// Asks the bot for a move. Use [`TbpBot::poll`] to play it once the bot
+    /// answers.
+    ///
+    /// The bot is sent the state of the game first if it didn't get it yet, or
+    /// if the game changed since the bot's last move, like when gravity placed
+    /// a tetromino or garbage rose. It's stopped and started again in that case.
+    pub fn suggest(&mut self, game: &Game) -> io::Result<()> {
+        let state = start_message(game);
+        if self.expected.as_ref() != Some(&state) {
+            if self.expected.is_some() {
+                self.send(&FrontendMessage::Stop)?;
+            }
+            self.send(&FrontendMessage::Start(state.clone()))?;
+            self.expected = Some(state.clone());
+        }
+
+        self.send(&FrontendMessage::Suggest)?;
+        self.asked = Some((state, Instant::now() + self.timeout));
+        Ok(())
+    }
+
+    /// Plays the first reachable move the bot suggested, if it answered.
+    /// Returns whether a move was played. The bot is told about tetrominoes
+    /// added to the next queue afterwards.
+    ///
+    /// Suggestions for a state the game isn't in anymore are dropped, so the bot
+    /// can be asked again. Fails if the bot didn't answer in time.
+    pub fn poll(&mut self, game: &mut Game) -> io::Result<bool> {
+        let Some((_, deadline)) = &self.asked else {
+            return Ok(false);
+        };
+        let message = match self.messages.try_recv() {
+            Ok(message) => message?,
+            Err(TryRecvError::Empty) if Instant::now() < *deadline => return Ok(false),
+            Err(TryRecvError::Empty) => return Err(timed_out()),
+            Err(TryRecvError::Disconnected) => return Err(closed()),
+        };
+        let moves = match message {
+            BotMessage::Suggestion { moves } => moves,
+            message => return Err(unexpected(&message)),
+        };
+        let (asked, _) = self.asked.take().expect("checked above");
+        if start_message(game) != asked {
+            return Ok(false);
+        }
+        let (played, placement) = moves
+            .into_iter()
+            .find_map(|played| Some((played, move_placement(game, &played)?)))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "bot suggested no reachable move",
+                )
+            })?;
+
+        // The board as the bot sees it after the move, which doesn't include
+        // garbage rising after the placement.
+        let mut known_board = game.board().clone();
+        known_board.place(placement.tetromino);
+        known_board.clear_complete();
+
+        // Holding for the first time takes a tetromino from the queue as well.
+        let first_hold = placement.inputs.contains(&Input::Hold) && game.held_tetromino().is_none();
+        let dealt = if first_hold { 2 } else { 1 };
+        for input in placement.inputs {
+            game.press(input);
+            game.release(input);
+        }
+        self.send(&FrontendMessage::Play { played })?;
+
+        if game.is_over() {
+            return Ok(true);
+        }
+        let visible = iter::once(game.falling_tetromino().tetromino)
+            .chain(game.queue())
+            .collect::<Vec<_>>();
+        for &piece in &visible[visible.len().saturating_sub(dealt)..] {
+            self.send(&FrontendMessage::NewPiece { piece })?;
+        }
+        self.expected = Some(Start {
+            board: board_rows(&known_board),
+            ..start_message(game)
+        });
+        Ok(true)
+    }
+}
+
+impl<W: Write> Drop for TbpBot<W> {
+    /// Tells the bot to exit. A spawned bot is waited for, and killed if it
+    /// doesn't exit within [`TbpBot::QUIT_TIMEOUT`].
+    fn drop(&mut self) {
+        let _ = self.send(&FrontendMessage::Quit);
+        let Some(child) = &mut self.child else {
+            return;
+        };
+        let deadline = Instant::now() + Self::QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                Ok(Some(_)) | Err(_) => return,
+            }
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+/// Reads messages from the bot and sends them to `sender`, until the bot
+/// closes its output, a message can't be read or the bot is dropped.
+fn read_messages(mut reader: impl BufRead, sender: Sender<io::Result<BotMessage>>) {
+    loop {
+        let mut line = String::new();
+        let message = match reader.read_line(&mut line) {
+            Ok(0) => Err(closed()),
+            Ok(_) => serde_json::from_str(&line).map_err(io::Error::from),
+            Err(err) => Err(err),
+        };
+        let failed = message.is_err();
+        if sender.send(message).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Creates the error for a bot that closed its output.
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "bot closed its output")
+}
+
+/// Creates the error for a bot that didn't answer in time.
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "bot didn't answer in time")
+}
+
+/// Creates the error for a message the bot wasn't supposed to send.
+fn unexpected(message: &BotMessage) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected message from bot: {message:?}"),
+    )
+}
+
+/// A game played by an external bot.
+///
+/// These games aren't saved on exit, since the bot's process and its view of
+/// the game can't be restored.
+pub struct TbpGame<W: Write> {
+    game: Game,
+    /// The bot, until it made a mistake or stopped answering.
+    bot: Option<TbpBot<W>>,
+    /// Ticks since the bot's last move.
+    ticks: u32,
+}
+
+impl<W: Write> TbpGame<W> {
+    /// Ticks between moves of the bot, so the game can be followed.
+    const MOVE_DELAY: u32 = 10;
+
+    /// Starts a game for `bot`. Fails unless the board has the default size
+    /// and the rotation system is SRS, since TBP only describes 10x40 boards (20
+    /// visible rows and a 20 row buffer) and bots assume SRS rotation states and
+    /// kicks.
+    pub fn new(settings: Settings, bot: TbpBot<W>) -> io::Result<Self> {
+        if (settings.board_width, settings.board_height)
+            != (Board::DEFAULT_WIDTH, Board::DEFAULT_HEIGHT)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "TBP needs a {}x{} board",
+                    Board::DEFAULT_WIDTH,
+                    Board::DEFAULT_HEIGHT
+                ),
+            ));
+        }
+        if settings.rotation_system != &Srs as &dyn RotationSystem {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("TBP needs SRS, not {}", settings.rotation_system.name()),
+            ));
+        }
+        Ok(Self {
+            game: Game::new(settings),
+            bot: Some(bot),
+            ticks: 0,
+        })
+    }
+
+    /// Saves the replay of the game in `replays` once it ends.
+    pub fn with_replays(mut self, replays: Option<Archive>) -> Self {
+        self.game = self.game.with_replays(replays);
+        self
+    }
+}
+
+impl<W: Write> Scene for TbpGame<W> {
+    fn keyboard_input(&mut self, input: KeyboardInput) -> Action {
+        // Exit [Esc] (temporary)
+        if let (1, ElementState::Pressed) = (input.scancode, input.state) {
+            return Action::Exit;
+        }
+        Action::Continue
+    }
+
+    /// Asks the bot for a move every [`TbpGame::MOVE_DELAY`] ticks and plays it
+    /// once the bot answers, without waiting for it.
+    fn tick(&mut self) -> Action {
+        self.ticks += 1;
+        if let Some(bot) = self.bot.as_mut().filter(|_| !self.game.is_over()) {
+            let result = if bot.is_thinking() {
+                bot.poll(&mut self.game).map(|played| {
+                    if played {
+                        self.ticks = 0;
+                    }
+                })
+            } else if self.ticks >= Self::MOVE_DELAY {
+                bot.suggest(&self.game)
+            } else {
+                Ok(())
+            };
+            if let Err(err) = result {
+                log::error!("bot {} stopped playing: {err}", bot.name());
+                self.bot = None;
+            }
+        }
+        self.game.tick()
+    }
+
+    fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError> {
+        self.game.render(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::rotation::Ars;
+
+    /// A minimal bot that drops every tetromino unrotated where it lands
+    /// lowest, and never holds.
+    fn stub_bot(reader: impl BufRead, mut writer: impl Write) {
+        const WIDTH: i32 = 10;
+        const HEIGHT: i32 = 40;
+
+        let mut send = |message: BotMessage| {
+            serde_json::to_writer(&mut writer, &message).unwrap();
+            writeln!(writer).unwrap();
+        };
+        send(BotMessage::Info {
+            name: "stub".to_string(),
+            version: "0.1".to_string(),
+            author: "gridt".to_string(),
+            features: Vec::new(),
+        });
+
+        let mut board = vec![[false; WIDTH as usize]; HEIGHT as usize];
+        let mut queue = VecDeque::new();
+        let fits = |board: &[[bool; WIDTH as usize]], location: &PieceLocation| {
+            location.tbp_squares().iter().all(|square| {
+                (0..WIDTH).contains(&square.x)
+                    && (0..HEIGHT).contains(&square.y)
+                    && !board[square.y as usize][square.x as usize]
+            })
+        };
+
+        for line in reader.lines() {
+            match serde_json::from_str(&line.unwrap()).unwrap() {
+                FrontendMessage::Rules => send(BotMessage::Ready),
+                FrontendMessage::Start(start) => {
+                    for (row, squares) in board.iter_mut().zip(&start.board) {
+                        for (square, cell) in row.iter_mut().zip(squares) {
+                            *square = cell.is_some();
+                        }
+                    }
+                    queue = start.queue.into();
+                }
+                FrontendMessage::Suggest => {
+                    let landings = (0..WIDTH).filter_map(|x| {
+                        let mut location = PieceLocation {
+                            tetromino: queue[0],
+                            orientation: Orientation::North,
+                            x,
+                            y: HEIGHT - 2,
+                        };
+                        if !fits(&board, &location) {
+                            return None;
+                        }
+                        while location.y > 0
+                            && fits(
+                                &board,
+                                &PieceLocation {
+                                    y: location.y - 1,
+                                    ..location
+                                },
+                            )
+                        {
+                            location.y -= 1;
+                        }
+                        Some(location)
+                    });
+                    let location = landings.min_by_key(|location| location.y).unwrap();
+                    send(BotMessage::Suggestion {
+                        moves: vec![Move {
+                            location,
+                            spin: Spin::None,
+                        }],
+                    });
+                }
+                FrontendMessage::Play { played } => {
+                    for square in played.location.tbp_squares() {
+                        board[square.y as usize][square.x as usize] = true;
+                    }
+                    board.retain(|row| row.contains(&false));
+                    board.resize(HEIGHT as usize, [false; WIDTH as usize]);
+                    queue.pop_front();
+                }
+                FrontendMessage::NewPiece { piece } => queue.push_back(piece),
+                FrontendMessage::Stop => (),
+                FrontendMessage::Quit => break,
+            }
+        }
+    }
+
+    /// Asks `bot` for a move and waits until it answered.
+    fn play(bot: &mut TbpBot<impl Write>, game: &mut Game) -> io::Result<()> {
+        bot.suggest(game)?;
+        while bot.is_thinking() {
+            bot.poll(game)?;
+            thread::sleep(Duration::from_millis(1));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn squares() {
+        let board = Board::default();
+        let location = PieceLocation {
+            tetromino: Tetromino::T,
+            orientation: Orientation::East,
+            x: 4,
+            y: 1,
+        };
+        assert_eq!(
+            sort_squares(location.squares(&board)),
+            [ivec2(4, 17), ivec2(4, 18), ivec2(4, 19), ivec2(5, 18)]
+        );
+    }
+
+    #[test]
+    fn plays_with_stub_bot() {
+        let (frontend_reader, bot_writer) = io::pipe().unwrap();
+        let (bot_reader, frontend_writer) = io::pipe().unwrap();
+        let stub = thread::spawn(move || stub_bot(BufReader::new(bot_reader), bot_writer));
+
+        let mut game = Game::new(Settings {
+            seed: Some(9),
+            ..Settings::default()
+        });
+        let mut bot = TbpBot::new(BufReader::new(frontend_reader), frontend_writer).unwrap();
+        assert_eq!(bot.name(), "stub");
+        for _ in 0..30 {
+            play(&mut bot, &mut game).unwrap();
+        }
+        assert_eq!(game.progress().statistics.pieces, 30);
+        assert!(!game.is_over());
+
+        drop(bot);
+        stub.join().unwrap();
+    }
+
+    #[test]
+    fn restarts_bot_after_garbage() {
+        let (frontend_reader, bot_writer) = io::pipe().unwrap();
+        let (bot_reader, frontend_writer) = io::pipe().unwrap();
+        let stub = thread::spawn(move || stub_bot(BufReader::new(bot_reader), bot_writer));
+
+        let mut game = Game::new(Settings {
+            seed: Some(9),
+            ..Settings::default()
+        });
+        let mut bot = TbpBot::new(BufReader::new(frontend_reader), frontend_writer).unwrap();
+        for _ in 0..5 {
+            play(&mut bot, &mut game).unwrap();
+        }
+        // The garbage rises after the next placement, behind the bot's back.
+        game.receive_garbage(4);
+        for _ in 0..10 {
+            play(&mut bot, &mut game).unwrap();
+        }
+        assert!(game.board().garbage_rows() > 0);
+        assert_eq!(game.progress().statistics.pieces, 15);
+
+        drop(bot);
+        stub.join().unwrap();
+    }
+
+    #[test]
+    fn refuses_unsupported_settings() {
+        for settings in [
+            Settings {
+                board_width: 6,
+                ..Settings::default()
+            },
+            Settings {
+                rotation_system: &Ars,
+                ..Settings::default()
+            },
+        ] {
+            let (frontend_reader, bot_writer) = io::pipe().unwrap();
+            let (bot_reader, frontend_writer) = io::pipe().unwrap();
+            let stub = thread::spawn(move || stub_bot(BufReader::new(bot_reader), bot_writer));
+
+            let bot = TbpBot::new(BufReader::new(frontend_reader), frontend_writer).unwrap();
+            let err = TbpGame::new(settings, bot).err().unwrap();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+            stub.join().unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn kills_bot_ignoring_quit() {
+        let script = r#"echo '{"type":"info","name":"stubborn","version":"0.1","author":"gridt","features":[]}'
+            echo '{"type":"ready"}'
+            exec sleep 60"#;
+        let bot = TbpBot::spawn("sh", &["-c", script]).unwrap();
+        assert_eq!(bot.name(), "stubborn");
+
+        let start = Instant::now();
+        drop(bot);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn asks_again_after_game_changed() {
+        let (frontend_reader, bot_writer) = io::pipe().unwrap();
+        let (bot_reader, frontend_writer) = io::pipe().unwrap();
+        let stub = thread::spawn(move || stub_bot(BufReader::new(bot_reader), bot_writer));
+
+        let mut game = Game::new(Settings::default());
+        let mut bot = TbpBot::new(BufReader::new(frontend_reader), frontend_writer).unwrap();
+        bot.suggest(&game).unwrap();
+        // The tetromino is placed while the bot is thinking.
+        game.press(Input::HardDrop);
+        game.release(Input::HardDrop);
+        while bot.is_thinking() {
+            assert!(!bot.poll(&mut game).unwrap());
+        }
+        play(&mut bot, &mut game).unwrap();
+        assert_eq!(game.progress().statistics.pieces, 2);
+
+        drop(bot);
+        stub.join().unwrap();
+    }
+
+    #[test]
+    fn gives_up_on_silent_bot() {
+        let (frontend_reader, mut bot_writer) = io::pipe().unwrap();
+        let (bot_reader, frontend_writer) = io::pipe().unwrap();
+        let silent = thread::spawn(move || {
+            for message in [
+                r#"{"type":"info","name":"silent","version":"0.1","author":"gridt","features":[]}"#,
+                r#"{"type":"ready"}"#,
+            ] {
+                writeln!(bot_writer, "{message}").unwrap();
+            }
+            // Keep the output open until told to quit.
+            for line in BufReader::new(bot_reader).lines() {
+                if line.unwrap().contains("quit") {
+                    break;
+                }
+            }
+        });
+
+        let mut game = Game::new(Settings::default());
+        let mut bot = TbpBot::new(BufReader::new(frontend_reader), frontend_writer).unwrap();
+        bot.timeout = Duration::from_millis(50);
+        bot.suggest(&game).unwrap();
+        assert!(!bot.poll(&mut game).unwrap());
+        thread::sleep(Duration::from_millis(100));
+        let err = bot.poll(&mut game).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        drop(bot);
+        silent.join().unwrap();
+    }
+}
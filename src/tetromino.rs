@@ -1,8 +1,11 @@
-use glam::{ivec2, vec4, IVec2, Vec4};
-use rand::Rng;
+use glam::{vec4, IVec2, Vec4};
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use crate::rotation::RotationSystem;
 
 /// A tetromino.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Tetromino {
     I,
     J,
@@ -14,7 +17,7 @@ pub enum Tetromino {
 }
 
 impl Tetromino {
-    const VARIANTS: [Tetromino; 7] = [
+    pub const VARIANTS: [Tetromino; 7] = [
         Tetromino::I,
         Tetromino::J,
         Tetromino::L,
@@ -25,8 +28,8 @@ impl Tetromino {
     ];
 
     /// Returns a random tetromino.
-    pub fn random() -> Self {
-        Self::VARIANTS[rand::thread_rng().gen_range(0..Self::VARIANTS.len())]
+    pub fn random(rng: &mut dyn RngCore) -> Self {
+        Self::VARIANTS[rng.gen_range(0..Self::VARIANTS.len())]
     }
 
     /// Returns the color this tetromino.
@@ -41,90 +44,52 @@ impl Tetromino {
             Tetromino::S => vec4(0.2, 0.9, 0.2, 1.0),
         }
     }
-
-    /// Returns the positions of squares which represent this tetromino on a 4x4
-    /// grid.
-    // TODO Provide graphical example of tetromino representation.
-    pub fn squares(self, rotation: u8) -> [IVec2; 4] {
-        match (self, rotation % 4) {
-            (Tetromino::I, 0 | 2) => [ivec2(0, 2), ivec2(1, 2), ivec2(2, 2), ivec2(3, 2)],
-            (Tetromino::I, 1 | 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(2, 3)],
-            (Tetromino::O, _) => [ivec2(1, 1), ivec2(2, 1), ivec2(1, 2), ivec2(2, 2)],
-            (Tetromino::J, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(3, 2)],
-            (Tetromino::J, 1) => [ivec2(2, 0), ivec2(2, 1), ivec2(1, 2), ivec2(2, 2)],
-            (Tetromino::J, 2) => [ivec2(1, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
-            (Tetromino::J, 3) => [ivec2(2, 0), ivec2(3, 0), ivec2(2, 1), ivec2(2, 2)],
-            (Tetromino::L, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(1, 2)],
-            (Tetromino::L, 1) => [ivec2(1, 0), ivec2(2, 0), ivec2(2, 1), ivec2(2, 2)],
-            (Tetromino::L, 2) => [ivec2(3, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
-            (Tetromino::L, 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(2, 2), ivec2(3, 2)],
-            (Tetromino::S, 0 | 2) => [ivec2(2, 1), ivec2(3, 1), ivec2(1, 2), ivec2(2, 2)],
-            (Tetromino::S, 1 | 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(3, 1), ivec2(3, 2)],
-            (Tetromino::Z, 0 | 2) => [ivec2(1, 1), ivec2(2, 1), ivec2(2, 2), ivec2(3, 2)],
-            (Tetromino::Z, 1 | 3) => [ivec2(3, 0), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
-            (Tetromino::T, 0) => [ivec2(1, 1), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
-            (Tetromino::T, 1) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(2, 2)],
-            (Tetromino::T, 2) => [ivec2(2, 0), ivec2(1, 1), ivec2(2, 1), ivec2(3, 1)],
-            (Tetromino::T, 3) => [ivec2(2, 0), ivec2(2, 1), ivec2(3, 1), ivec2(2, 2)],
-            _ => unreachable!(),
-        }
-    }
-
-    /// Returns the width of this tetromino as the number of squares.
-    pub fn width(self, rotation: u8) -> u8 {
-        match (self, rotation % 4) {
-            (Tetromino::I, 0 | 2) => 4,
-            (Tetromino::I, 1 | 3) => 1,
-            (Tetromino::O, _) => 2,
-            (_, 0 | 2) => 3,
-            (_, 1 | 3) => 2,
-            _ => unreachable!(),
-        }
-    }
 }
 
 /// A falling tetromino.
 ///
-/// Unlike [`Tetromino`], [`FallingTetromino`] has a position and rotation.
-#[derive(Clone, Copy, Debug)]
+/// Unlike [`Tetromino`], [`FallingTetromino`] has a position and rotation. Its
+/// shape is determined by the rotation system it was spawned with.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct FallingTetromino {
     position: IVec2,
     rotation: u8,
     pub tetromino: Tetromino,
+    #[serde(with = "crate::rotation::by_name")]
+    pub rotation_system: &'static dyn RotationSystem,
 }
 
 impl FallingTetromino {
-    /// The starting position of a falling tetromino.
-    const ORIGIN: IVec2 = ivec2(3, -1);
-
-    /// Creates a new falling tetromino positioned at [`Self::ORIGIN`].
-    pub fn new_at_origin(tetromino: Tetromino) -> Self {
+    /// Creates a new falling tetromino at the spawn position of `rotation_system`
+    /// on a board `board_width` squares wide.
+    pub fn spawn(
+        tetromino: Tetromino,
+        rotation_system: &'static dyn RotationSystem,
+        board_width: usize,
+    ) -> Self {
         Self {
-            position: Self::ORIGIN,
+            position: rotation_system.spawn_position(tetromino, board_width),
             rotation: 0,
             tetromino,
+            rotation_system,
         }
     }
 
-    /// Creates a new falling tetromino with a random shape positioned at
-    /// [`Self::ORIGIN`].
-    pub fn random_at_origin() -> Self {
-        Self {
-            position: ivec2(3, -1),
-            rotation: 0,
-            tetromino: Tetromino::random(),
-        }
+    /// Returns the position of the 4x4 grid this tetromino's shape is defined on.
+    pub fn position(&self) -> IVec2 {
+        self.position
+    }
+
+    /// Returns the rotation state of this tetromino (0 to 3).
+    pub fn rotation(&self) -> u8 {
+        self.rotation % 4
     }
 
     /// Returns the positions of squares representing this tetromino.
     pub fn squares(&self) -> [IVec2; 4] {
-        let squares = self.tetromino.squares(self.rotation);
-        [
-            squares[0] + self.position,
-            squares[1] + self.position,
-            squares[2] + self.position,
-            squares[3] + self.position,
-        ]
+        self.rotation_system
+            .squares(self.tetromino, self.rotation)
+            .map(|square| square + self.position)
     }
 
     /// Returns a new rotated instance of this tetromino.
@@ -133,7 +98,7 @@ impl FallingTetromino {
     /// (1 = 90 deg. clockwise, -1 = 90 deg. counterclockwise).
     pub fn rotated(self, by: i8) -> FallingTetromino {
         FallingTetromino {
-            rotation: self.rotation.wrapping_add_signed(by),
+            rotation: self.rotation.wrapping_add_signed(by) % 4,
             ..self
         }
     }
@@ -0,0 +1,203 @@
+//! Local two-player versus: line clears send garbage to the opponent, and the
+//! last player standing wins. Matches are recorded like single-player games, with
+//! the inputs of both players.
+
+use glam::vec2;
+use wgpu::SurfaceError;
+use winit::event::{ElementState, KeyboardInput};
+
+use crate::{
+    archive::Archive,
+    bot::{Bot, Weights},
+    game::Game,
+    game_over::GameOver,
+    input::KeyMap,
+    mode::{ModeKind, Progress, Results},
+    render::context::RenderContext,
+    replay::Recording,
+    scene::{Action, Scene},
+    settings::Settings,
+};
+
+/// Two games side by side, each controlled with its own half of the keyboard or
+/// the left one against a bot.
+///
+/// Matches aren't saved on exit like single-player games, so exiting in the
+/// middle of a match ends it without a replay.
+pub struct Versus {
+    /// The settings the match was started with, for rematches.
+    settings: Settings,
+    seed: u64,
+    /// The left and right player.
+    players: [Game; 2],
+    /// Controls the right player instead of the keyboard, if there is one.
+    bot: Option<Bot>,
+    /// Where the replay is saved once the match ends, if anywhere.
+    replays: Option<Archive>,
+}
+
+impl Versus {
+    /// Ticks between inputs of the bot opponent.
+    const BOT_INPUT_DELAY: u32 = 6;
+
+    /// Starts a match where both players get the same tetrominoes. Games
+    /// always run in marathon mode, so they only end by topping out.
+    pub fn new(settings: Settings) -> Self {
+        Self::start(settings, [KeyMap::player_one(), KeyMap::player_two()], None)
+    }
+
+    /// Starts a match where the right player is controlled by a bot. The left
+    /// player gets all the keys of a single-player game.
+    pub fn against_bot(settings: Settings) -> Self {
+        Self::start(
+            settings,
+            [KeyMap::default(), KeyMap::player_two()],
+            Some(Bot::new(Weights::default(), Self::BOT_INPUT_DELAY)),
+        )
+    }
+
+    /// Starts a match with the left and right player's key maps.
+    fn start(settings: Settings, key_maps: [KeyMap; 2], bot: Option<Bot>) -> Self {
+        let seed = settings.seed.unwrap_or_else(rand::random);
+        let game_settings = Settings {
+            mode: ModeKind::Marathon,
+            seed: Some(seed),
+            ..settings
+        };
+        Self {
+            settings,
+            seed,
+            players: key_maps.map(|key_map| Game::new(game_settings).with_key_map(key_map)),
+            bot,
+            replays: None,
+        }
+    }
+
+    /// Saves the replay of this match in `replays` once it ends, if there is
+    /// one. Matches and their rematches don't save replays otherwise.
+    pub fn with_replays(mut self, replays: Option<Archive>) -> Self {
+        self.replays = replays;
+        self
+    }
+
+    /// Returns the results once a player topped out, or both at once.
+    fn results(&self) -> Option<Results> {
+        let title = match self.players.each_ref().map(Game::is_over) {
+            [false, false] => return None,
+            [true, true] => "Draw!",
+            [false, true] => "Player 1 wins!",
+            [true, false] => "Player 2 wins!",
+        };
+        let [left, right] = self.players.each_ref().map(Game::progress);
+        Some(Results {
+            title,
+            text: format!(
+                "Player 1: {}\nPlayer 2: {}",
+                summary(&left),
+                summary(&right)
+            ),
+        })
+    }
+}
+
+/// Passes the garbage each player sent to the other one.
+pub fn exchange_garbage(left: &mut Game, right: &mut Game) {
+    let sent_left = left.take_outgoing_garbage();
+    let sent_right = right.take_outgoing_garbage();
+    left.receive_garbage(sent_right);
+    right.receive_garbage(sent_left);
+}
+
+/// Summarizes what a player did in a match.
+fn summary(progress: &Progress) -> String {
+    format!(
+        "{} lines, {} sent, {} pieces",
+        progress.lines, progress.statistics.attack, progress.statistics.pieces
+    )
+}
+
+impl Scene for Versus {
+    fn keyboard_input(&mut self, input: KeyboardInput) -> Action {
+        // Exit [Esc] (temporary)
+        if let (1, ElementState::Pressed) = (input.scancode, input.state) {
+            return Action::Exit;
+        }
+
+        let human_players = if self.bot.is_some() { 1 } else { 2 };
+        for player in &mut self.players[..human_players] {
+            player.key_input(input);
+        }
+        Action::Continue
+    }
+
+    fn tick(&mut self) -> Action {
+        if let Some(results) = self.results() {
+            let [left, right] = &self.players;
+            if let Some(replays) = &self.replays {
+                if let Err(err) = Recording::versus(left, right).save(replays) {
+                    log::error!("couldn't save replay: {err}");
+                }
+            }
+            let rematch: fn(Settings, Option<Archive>) -> Box<dyn Scene> = if self.bot.is_some() {
+                |settings, replays| Box::new(Versus::against_bot(settings).with_replays(replays))
+            } else {
+                |settings, replays| Box::new(Versus::new(settings).with_replays(replays))
+            };
+            let game_over = GameOver::new(self.settings, results, self.seed, self.replays.clone())
+                .with_restart(rematch);
+            return Action::SwitchScene(Box::new(game_over));
+        }
+        if let Some(bot) = &mut self.bot {
+            bot.update(&mut self.players[1]);
+        }
+        for player in &mut self.players {
+            player.update();
+        }
+        let [left, right] = &mut self.players;
+        exchange_garbage(left, right);
+        Action::Continue
+    }
+
+    /// Renders each game centered in its half of the window.
+    fn render(&mut self, ctx: &mut RenderContext) -> Result<(), SurfaceError> {
+        let half_width = ctx.config.width as f32 / 2.0;
+        let x = ((half_width - Game::SIZE.x) / 2.0).max(0.0);
+        for (i, player) in self.players.iter().enumerate() {
+            player.render_at(ctx, vec2(i as f32 * half_width + x, 0.0));
+        }
+        ctx.render_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::event::ModifiersState;
+
+    use super::*;
+
+    #[allow(deprecated)]
+    fn press(scancode: u32) -> KeyboardInput {
+        KeyboardInput {
+            scancode,
+            state: ElementState::Pressed,
+            virtual_keycode: None,
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    #[test]
+    fn same_tetrominoes() {
+        let versus = Versus::new(Settings::default());
+        let [left, right] = &versus.players;
+        assert_eq!(left.seed(), right.seed());
+        assert_eq!(left.seed(), versus.seed);
+    }
+
+    #[test]
+    fn arrow_keys_against_bot() {
+        // [Left]
+        let left = press(57419);
+        assert!(!Versus::new(Settings::default()).players[0].key_input(left));
+        assert!(Versus::against_bot(Settings::default()).players[0].key_input(left));
+    }
+}